@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use crate::{ColorProfile, FanProfilePoint, ProfileInfo, SCHEMA_VERSION};
+
+/// A global profile together with all fan and LED profiles it references.
+/// Used to share complete setups between machines.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProfileBundle {
+    /// The schema version of the contained profiles.
+    pub version: u32,
+    /// The name of the global profile.
+    pub name: String,
+    pub profile: ProfileInfo,
+    pub fan_profiles: BTreeMap<String, Vec<FanProfilePoint>>,
+    pub led_profiles: BTreeMap<String, ColorProfile>,
+}
+
+impl ProfileBundle {
+    pub fn new(name: String, profile: ProfileInfo) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            name,
+            profile,
+            fan_profiles: BTreeMap::new(),
+            led_profiles: BTreeMap::new(),
+        }
+    }
+}
+
+/// What happens if a profile of an imported bundle
+/// already exists with a different content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ConflictPolicy {
+    /// Abort the import without changing anything.
+    #[default]
+    Fail,
+    /// Replace the existing profiles.
+    Overwrite,
+    /// Keep the existing profiles and reference them instead.
+    Skip,
+    /// Import the profiles with a new name.
+    Rename,
+}
@@ -1,19 +1,51 @@
-use crate::led::LedControllerMode;
+use crate::led::{LedChannel, LedControllerMode};
 use atoi::FromRadix16;
 use std::{fmt::Display, io, str::FromStr};
 
+const RGB_CHANNELS: [LedChannel; 3] = [LedChannel::Red, LedChannel::Green, LedChannel::Blue];
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct ColorPoint {
     pub color: Color,
     pub transition: ColorTransition,
     /// Transition time in ms.
     pub transition_time: u32,
+    /// Time in ms to keep the color after the transition.
+    #[serde(default)]
+    pub hold_time: u32,
+    /// The color space used for the transition to this color.
+    #[serde(default)]
+    pub interpolation: ColorInterpolation,
+}
+
+/// The color space used to calculate the colors between two color points.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ColorInterpolation {
+    /// Interpolate the raw sRGB values.
+    #[default]
+    Srgb,
+    /// Interpolate in linear RGB, which avoids dark transitions.
+    LinearRgb,
+    /// Interpolate hue, saturation and value and
+    /// take the shorter way around the color wheel.
+    Hsv,
+    /// Interpolate in the perceptually uniform OKLab color space.
+    Oklab,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum ColorTransition {
     None,
     Linear,
+    /// Start slow and speed up towards the end.
+    EaseIn,
+    /// Start fast and slow down towards the end.
+    EaseOut,
+    /// Start and end slow.
+    EaseInOut,
+    /// Fade the previous color out and the new color in again,
+    /// like a breathing pulse. The color changes while the LEDs are dark.
+    Breathing,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -21,6 +53,127 @@ pub enum ColorProfile {
     None,
     Single(Color),
     Multiple(Vec<ColorPoint>),
+    /// The color follows live system data.
+    Reactive(ReactiveProfile),
+    /// A separate profile for each zone of the device.
+    /// Zones without an entry are turned off.
+    Zones(Vec<ColorProfile>),
+}
+
+/// A short color pattern that interrupts the running color profile.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FlashPattern {
+    /// The colors of the pattern.
+    /// Like in [`ColorProfile::Multiple`], the first transition starts at the last color.
+    pub points: Vec<ColorPoint>,
+    /// How often the pattern is played.
+    pub repetitions: u32,
+}
+
+impl FlashPattern {
+    /// Blink the color `count` times.
+    pub fn blink(color: Color, count: u32) -> Self {
+        Self {
+            points: vec![
+                ColorPoint {
+                    color,
+                    transition: ColorTransition::None,
+                    transition_time: 250,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
+                },
+                ColorPoint {
+                    color: Color { r: 0, g: 0, b: 0 },
+                    transition: ColorTransition::None,
+                    transition_time: 250,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
+                },
+            ],
+            repetitions: count,
+        }
+    }
+
+    /// Fade the color in and out again within `duration` ms.
+    pub fn pulse(color: Color, duration: u32) -> Self {
+        Self {
+            points: vec![
+                ColorPoint {
+                    color,
+                    transition: ColorTransition::EaseInOut,
+                    transition_time: duration / 2,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
+                },
+                ColorPoint {
+                    color: Color { r: 0, g: 0, b: 0 },
+                    transition: ColorTransition::EaseInOut,
+                    transition_time: duration - duration / 2,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
+                },
+            ],
+            repetitions: 1,
+        }
+    }
+
+    /// The total duration of the pattern in ms.
+    pub fn duration(&self) -> u64 {
+        let single: u64 = self
+            .points
+            .iter()
+            .map(|point| point.transition_time as u64 + point.hold_time as u64)
+            .sum();
+        single * self.repetitions as u64
+    }
+}
+
+/// A color gradient that is sampled with a value from the system.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReactiveProfile {
+    pub source: ReactiveSource,
+    /// The colors of the gradient, sorted by value.
+    /// Values below the first or above the last point use the color of that point.
+    pub points: Vec<ReactivePoint>,
+    /// The color space used to calculate the colors between two points.
+    #[serde(default)]
+    pub interpolation: ColorInterpolation,
+}
+
+impl Default for ReactiveProfile {
+    /// Blue at 40°C to red at 90°C.
+    fn default() -> Self {
+        Self {
+            source: ReactiveSource::Temperature,
+            points: vec![
+                ReactivePoint {
+                    value: 40,
+                    color: Color { r: 0, g: 0, b: 255 },
+                },
+                ReactivePoint {
+                    value: 90,
+                    color: Color { r: 255, g: 0, b: 0 },
+                },
+            ],
+            interpolation: ColorInterpolation::Srgb,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ReactiveSource {
+    /// Temperature in °C of the first fan.
+    Temperature,
+    /// Battery level in percent.
+    Battery,
+    /// CPU load in percent.
+    CpuLoad,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReactivePoint {
+    pub value: u8,
+    pub color: Color,
 }
 
 impl ColorProfile {
@@ -32,16 +185,22 @@ impl ColorProfile {
                     color: Color { r: 255, g: 0, b: 0 },
                     transition: ColorTransition::Linear,
                     transition_time: 6000,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
                 },
                 ColorPoint {
                     color: Color { r: 0, g: 255, b: 0 },
                     transition: ColorTransition::Linear,
                     transition_time: 6000,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
                 },
                 ColorPoint {
                     color: Color { r: 0, g: 0, b: 255 },
                     transition: ColorTransition::Linear,
                     transition_time: 6000,
+                    hold_time: 0,
+                    interpolation: ColorInterpolation::Srgb,
                 },
             ]),
         }
@@ -56,15 +215,84 @@ pub struct Color {
 }
 
 impl Color {
-    pub fn sysfs_rgb_string(&self, max_brightness: u32) -> String {
+    /// Scale the color by a brightness value in percent.
+    /// Values above 100 are treated as 100.
+    pub fn scaled(&self, brightness: u8) -> Self {
+        let factor = brightness.min(100) as f32 / 100.0;
+        let scale = |value: u8| -> u8 { (value as f32 * factor).round() as u8 };
+        Self {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
+        }
+    }
+
+    /// Create the value for the sysfs `multi_intensity` file of an RGB device.
+    ///
+    /// The optional gamma value corrects the non-linear brightness
+    /// perception of LEDs, which is most noticeable for devices
+    /// with a low `max_brightness`.
+    pub fn sysfs_rgb_string(&self, max_brightness: u32, gamma: Option<f32>) -> String {
+        self.sysfs_channels_string(&RGB_CHANNELS, max_brightness, gamma)
+    }
+
+    /// Create the value for the sysfs `multi_intensity` file
+    /// of a device with the given channels.
+    ///
+    /// A white channel takes over the part of the color that is
+    /// shared by red, green and blue. See [`Color::sysfs_rgb_string`]
+    /// for the gamma value.
+    pub fn sysfs_channels_string(
+        &self,
+        channels: &[LedChannel],
+        max_brightness: u32,
+        gamma: Option<f32>,
+    ) -> String {
+        let values: Vec<String> = self
+            .channel_values(channels)
+            .into_iter()
+            .map(|value| {
+                if max_brightness == 255 && gamma.is_none() {
+                    value as u32
+                } else {
+                    let max = max_brightness as f32;
+                    let value = value as f32 / 255.0;
+                    let value = gamma.map_or(value, |gamma| value.powf(gamma));
+                    (value * max).clamp(0.0, max) as u32
+                }
+                .to_string()
+            })
+            .collect();
+        values.join(" ")
+    }
+
+    /// The value of each channel from 0 to 255.
+    fn channel_values(&self, channels: &[LedChannel]) -> Vec<u8> {
         let Color { r, g, b } = *self;
-        if max_brightness == 255 {
-            format!("{r} {g} {b}")
+        let has_rgb = channels.iter().any(|channel| {
+            matches!(
+                channel,
+                LedChannel::Red | LedChannel::Green | LedChannel::Blue
+            )
+        });
+        let white = if !channels.contains(&LedChannel::White) {
+            0
+        } else if has_rgb {
+            r.min(g).min(b)
         } else {
-            let max = max_brightness as f32;
-            let scale = |value: u8| -> u32 { (value as f32 / 255.0 * max).clamp(0.0, max) as u32 };
-            format!("{} {} {}", scale(r), scale(g), scale(b))
-        }
+            ([r, g, b].into_iter().map(u16::from).sum::<u16>() / 3) as u8
+        };
+
+        channels
+            .iter()
+            .map(|channel| match channel {
+                LedChannel::Red => r - white.min(r),
+                LedChannel::Green => g - white.min(g),
+                LedChannel::Blue => b - white.min(b),
+                LedChannel::White => white,
+                LedChannel::Other(_) => 0,
+            })
+            .collect()
     }
 
     pub fn sysfs_monochrome_string(&self, max_brightness: u32) -> String {
@@ -79,27 +307,45 @@ impl Color {
         }
     }
 
-    pub fn from_sysfs_rgb_value(values: [u32; 3], max_brightness: u32) -> Self {
-        if max_brightness == 255 {
-            let values: Vec<u8> = values
-                .into_iter()
-                .map(u8::try_from)
-                .map(Result::unwrap_or_default)
-                .collect();
-            Self {
-                r: values[0],
-                g: values[1],
-                b: values[2],
+    /// Inverse of [`Color::sysfs_rgb_string`].
+    pub fn from_sysfs_rgb_value(values: [u32; 3], max_brightness: u32, gamma: Option<f32>) -> Self {
+        Self::from_sysfs_channel_values(&values, &RGB_CHANNELS, max_brightness, gamma)
+    }
+
+    /// Inverse of [`Color::sysfs_channels_string`].
+    pub fn from_sysfs_channel_values(
+        values: &[u32],
+        channels: &[LedChannel],
+        max_brightness: u32,
+        gamma: Option<f32>,
+    ) -> Self {
+        let scale = |value: u32| -> u8 {
+            if max_brightness == 255 && gamma.is_none() {
+                u8::try_from(value).unwrap_or_default()
+            } else {
+                let max = max_brightness as f32;
+                let value = value as f32 / max;
+                let value = gamma.map_or(value, |gamma| value.powf(gamma.recip()));
+                (value * 255.0).clamp(0.0, 255.0) as u8
             }
-        } else {
-            let max = max_brightness as f32;
-            let scale = |value: u32| -> u8 { (value as f32 / max * 255.0).clamp(0.0, 255.0) as u8 };
-            Self {
-                r: scale(values[0]),
-                g: scale(values[1]),
-                b: scale(values[2]),
+        };
+
+        let mut color = Self { r: 0, g: 0, b: 0 };
+        for (channel, value) in channels.iter().zip(values) {
+            let value = scale(*value);
+            match channel {
+                LedChannel::Red => color.r = color.r.saturating_add(value),
+                LedChannel::Green => color.g = color.g.saturating_add(value),
+                LedChannel::Blue => color.b = color.b.saturating_add(value),
+                LedChannel::White => {
+                    color.r = color.r.saturating_add(value);
+                    color.g = color.g.saturating_add(value);
+                    color.b = color.b.saturating_add(value);
+                }
+                LedChannel::Other(_) => {}
             }
         }
+        color
     }
 }
 
@@ -141,7 +387,8 @@ impl FromStr for Color {
 
 #[cfg(test)]
 mod test {
-    use crate::color::Color;
+    use crate::color::{Color, ColorInterpolation, ColorPoint, ColorTransition, FlashPattern};
+    use crate::led::LedChannel;
     use std::str::FromStr;
 
     #[test]
@@ -162,4 +409,96 @@ mod test {
         Color::from_str("F").unwrap_err();
         Color::from_str("INVLD!").unwrap_err();
     }
+
+    #[test]
+    fn color_scaled() {
+        let color = Color {
+            r: 255,
+            g: 100,
+            b: 0,
+        };
+        assert_eq!(color.scaled(100), color);
+        assert_eq!(color.scaled(200), color);
+        assert_eq!(color.scaled(0), Color { r: 0, g: 0, b: 0 });
+        assert_eq!(
+            color.scaled(50),
+            Color {
+                r: 128,
+                g: 50,
+                b: 0
+            }
+        );
+    }
+
+    #[test]
+    fn gamma_correction() {
+        let color = Color {
+            r: 255,
+            g: 128,
+            b: 0,
+        };
+        assert_eq!(color.sysfs_rgb_string(255, None), "255 128 0");
+        assert_eq!(color.sysfs_rgb_string(255, Some(2.2)), "255 55 0");
+        assert_eq!(color.sysfs_rgb_string(50, Some(2.2)), "50 10 0");
+
+        let restored = Color::from_sysfs_rgb_value([255, 55, 0], 255, Some(2.2));
+        assert_eq!(restored.r, 255);
+        assert!(restored.g.abs_diff(128) <= 2);
+        assert_eq!(restored.b, 0);
+    }
+
+    #[test]
+    fn color_point_without_hold_time() {
+        let json =
+            r#"{"color":{"r":255,"g":0,"b":0},"transition":"Linear","transition_time":1000}"#;
+        let color_point: ColorPoint = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            color_point,
+            ColorPoint {
+                color: Color { r: 255, g: 0, b: 0 },
+                transition: ColorTransition::Linear,
+                transition_time: 1000,
+                hold_time: 0,
+                interpolation: ColorInterpolation::Srgb,
+            }
+        );
+    }
+
+    #[test]
+    fn flash_pattern_duration() {
+        let color = Color { r: 255, g: 0, b: 0 };
+        assert_eq!(FlashPattern::blink(color.clone(), 3).duration(), 1500);
+        assert_eq!(FlashPattern::pulse(color, 2001).duration(), 2001);
+    }
+
+    #[test]
+    fn channel_layouts() {
+        let color = Color {
+            r: 255,
+            g: 100,
+            b: 50,
+        };
+
+        let grb = [LedChannel::Green, LedChannel::Red, LedChannel::Blue];
+        assert_eq!(color.sysfs_channels_string(&grb, 255, None), "100 255 50");
+        assert_eq!(
+            Color::from_sysfs_channel_values(&[100, 255, 50], &grb, 255, None),
+            color
+        );
+
+        let rgbw = [
+            LedChannel::Red,
+            LedChannel::Green,
+            LedChannel::Blue,
+            LedChannel::White,
+        ];
+        assert_eq!(color.sysfs_channels_string(&rgbw, 255, None), "205 50 0 50");
+        assert_eq!(
+            Color::from_sysfs_channel_values(&[205, 50, 0, 50], &rgbw, 255, None),
+            color
+        );
+
+        let unknown = [LedChannel::Red, LedChannel::Other("amber".to_owned())];
+        assert_eq!(color.sysfs_channels_string(&unknown, 255, None), "255 0");
+    }
 }
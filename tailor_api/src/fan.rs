@@ -3,3 +3,35 @@ pub struct FanProfilePoint {
     pub temp: u8,
     pub fan: u8,
 }
+
+/// What a fan cools. Profiles that assign fan profiles by role
+/// work on devices with a different number or order of fans.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum FanRole {
+    Cpu,
+    Gpu,
+}
+
+impl std::fmt::Display for FanRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FanRole::Cpu => f.write_str("cpu"),
+            FanRole::Gpu => f.write_str("gpu"),
+        }
+    }
+}
+
+impl std::str::FromStr for FanRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cpu" => Ok(FanRole::Cpu),
+            "gpu" => Ok(FanRole::Gpu),
+            _ => Err(format!("Unknown fan role `{s}`, expected cpu or gpu")),
+        }
+    }
+}
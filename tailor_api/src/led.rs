@@ -1,3 +1,5 @@
+use crate::LedProfile;
+
 #[derive(
     Default, Debug, Copy, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash,
 )]
@@ -13,6 +15,80 @@ pub struct LedDeviceInfo {
     pub device_name: String,
     pub function: String,
     pub mode: LedControllerMode,
+    /// The number of individually controllable zones,
+    /// ordered as reported by the kernel.
+    #[serde(default = "default_zones")]
+    pub zones: u32,
+    /// How the zones are arranged on the device.
+    #[serde(default)]
+    pub layout: LedZoneLayout,
+    /// The color channels in the order of the `multi_index` file.
+    /// Empty for monochrome devices.
+    #[serde(default)]
+    pub channels: Vec<LedChannel>,
+}
+
+/// How the zones of an LED device are arranged.
+#[derive(
+    Default, Debug, Clone, Copy, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub enum LedZoneLayout {
+    /// The device has a single zone.
+    #[default]
+    Single,
+    /// The zones are side by side, ordered from left to right.
+    Row,
+    /// One zone per key, ordered row by row starting at the top left.
+    Keys { rows: u32, columns: u32 },
+}
+
+impl LedZoneLayout {
+    /// The row and column of a zone, both starting at 0.
+    pub fn position(&self, zone: u32) -> (u32, u32) {
+        match self {
+            Self::Single | Self::Row => (0, zone),
+            Self::Keys { columns, .. } => (zone / columns.max(&1), zone % columns.max(&1)),
+        }
+    }
+
+    /// A short description of where a zone is on the device.
+    pub fn describe(&self, zone: u32) -> String {
+        let (row, column) = self.position(zone);
+        match self {
+            Self::Single => "Whole device".to_owned(),
+            Self::Row => format!("Zone {} from the left", column + 1),
+            Self::Keys { .. } => format!("Key {} in row {}", column + 1, row + 1),
+        }
+    }
+}
+
+/// A color channel of a multicolor LED device.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LedChannel {
+    Red,
+    Green,
+    Blue,
+    White,
+    /// A channel that can't be mapped to a color.
+    /// These channels are turned off.
+    Other(String),
+}
+
+impl LedChannel {
+    /// Parse a channel name of the sysfs `multi_index` file.
+    pub fn from_sysfs_name(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "red" => Self::Red,
+            "green" => Self::Green,
+            "blue" => Self::Blue,
+            "white" => Self::White,
+            other => Self::Other(other.to_owned()),
+        }
+    }
+}
+
+fn default_zones() -> u32 {
+    1
 }
 
 impl LedDeviceInfo {
@@ -21,7 +97,137 @@ impl LedDeviceInfo {
             device_name,
             function,
             mode: _mode,
+            zones: _zones,
+            layout: _layout,
+            channels: _channels,
         } = self;
         format!("{device_name}::{function}")
     }
 }
+
+/// How well a profile entry matches an LED device, from best to worst.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum LedMatch {
+    /// Device name and function are equal.
+    Exact,
+    /// Only the function is equal, e.g. after the device was renamed.
+    Function,
+    /// Only the mode is equal.
+    Mode,
+}
+
+impl LedProfile {
+    /// How well this profile entry matches an LED device.
+    pub fn match_device(&self, device: &LedDeviceInfo) -> Option<LedMatch> {
+        if self.function == device.function {
+            if self.device_name == device.device_name {
+                Some(LedMatch::Exact)
+            } else {
+                Some(LedMatch::Function)
+            }
+        } else if self.mode == device.mode {
+            Some(LedMatch::Mode)
+        } else {
+            None
+        }
+    }
+}
+
+/// Assign the profile entries to the devices.
+///
+/// Better matches are assigned first and each entry is used for
+/// at most one device. Returns the index of the entry and the
+/// quality of the match for each device.
+pub fn match_led_profiles(
+    entries: &[LedProfile],
+    devices: &[LedDeviceInfo],
+) -> Vec<Option<(usize, LedMatch)>> {
+    let mut assignments = vec![None; devices.len()];
+    let mut used = vec![false; entries.len()];
+
+    for quality in [LedMatch::Exact, LedMatch::Function, LedMatch::Mode] {
+        for (device, assignment) in devices.iter().zip(assignments.iter_mut()) {
+            if assignment.is_some() {
+                continue;
+            }
+            let entry = entries
+                .iter()
+                .enumerate()
+                .position(|(idx, entry)| !used[idx] && entry.match_device(device) == Some(quality));
+            if let Some(idx) = entry {
+                used[idx] = true;
+                *assignment = Some((idx, quality));
+            }
+        }
+    }
+    assignments
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{LedControllerMode, LedDeviceInfo, LedProfile, LedZoneLayout};
+
+    use super::{match_led_profiles, LedMatch};
+
+    fn entry(device_name: &str, function: &str, mode: LedControllerMode) -> LedProfile {
+        LedProfile {
+            device_name: device_name.to_owned(),
+            function: function.to_owned(),
+            profile: "default".to_owned(),
+            mode,
+            brightness: None,
+            gamma_correction: false,
+            phase_offset: 0,
+        }
+    }
+
+    fn device(device_name: &str, function: &str, mode: LedControllerMode) -> LedDeviceInfo {
+        LedDeviceInfo {
+            device_name: device_name.to_owned(),
+            function: function.to_owned(),
+            mode,
+            zones: 1,
+            layout: LedZoneLayout::Single,
+            channels: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn zone_positions() {
+        let keys = LedZoneLayout::Keys {
+            rows: 6,
+            columns: 21,
+        };
+        assert_eq!(keys.position(0), (0, 0));
+        assert_eq!(keys.position(22), (1, 1));
+        assert_eq!(keys.describe(125), "Key 21 in row 6");
+        assert_eq!(LedZoneLayout::Row.describe(2), "Zone 3 from the left");
+    }
+
+    #[test]
+    fn matching_priorities() {
+        let entries = [
+            entry("old_name", "kbd_backlight", LedControllerMode::Rgb),
+            entry("lightbar", "lightbar", LedControllerMode::Rgb),
+            entry("other", "status", LedControllerMode::Monochrome),
+        ];
+        let devices = [
+            device("new_name", "kbd_backlight", LedControllerMode::Rgb),
+            device("usb", "something", LedControllerMode::Monochrome),
+            device("lightbar", "lightbar", LedControllerMode::Rgb),
+            device("usb", "else", LedControllerMode::Rgb),
+        ];
+
+        assert_eq!(
+            match_led_profiles(&entries, &devices),
+            [
+                Some((0, LedMatch::Function)),
+                Some((2, LedMatch::Mode)),
+                Some((1, LedMatch::Exact)),
+                None,
+            ]
+        );
+    }
+}
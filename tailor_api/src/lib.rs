@@ -1,9 +1,27 @@
+mod bundle;
 mod color;
 mod fan;
 mod led;
 mod profile;
+mod rule;
+mod schedule;
+mod schema;
 
-pub use color::{Color, ColorPoint, ColorProfile, ColorTransition};
-pub use fan::FanProfilePoint;
-pub use led::{LedControllerMode, LedDeviceInfo};
-pub use profile::{LedProfile, ProfileInfo};
+pub use bundle::{ConflictPolicy, ProfileBundle};
+pub use color::{
+    Color, ColorInterpolation, ColorPoint, ColorProfile, ColorTransition, FlashPattern,
+    ReactivePoint, ReactiveProfile, ReactiveSource,
+};
+pub use fan::{FanProfilePoint, FanRole};
+pub use led::{
+    match_led_profiles, LedChannel, LedControllerMode, LedDeviceInfo, LedMatch, LedZoneLayout,
+};
+pub use profile::{
+    LedProfile, ProfileDefinition, ProfileEntry, ProfileInfo, ProfileOrigin, RemovalStrategy,
+    RevertCondition, TemporaryProfile,
+};
+pub use rule::ProcessRule;
+pub use schedule::{ProfileSchedule, TimeOfDay, Weekday};
+pub use schema::{
+    to_versioned_json, upgrade_profile, ProfileKind, SchemaError, UpgradedProfile, SCHEMA_VERSION,
+};
@@ -1,8 +1,16 @@
-use crate::LedControllerMode;
+use std::collections::BTreeMap;
+
+use crate::{FanRole, LedControllerMode};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct ProfileInfo {
+    /// Fan profiles in the order of the fans.
+    /// Used for fans whose role isn't listed in `fan_roles`.
+    #[serde(default)]
     pub fans: Vec<String>,
+    /// Fan profiles by the role of the fan, like `{ "cpu": "silent" }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fan_roles: BTreeMap<FanRole, String>,
     pub leds: Vec<LedProfile>,
     pub performance_profile: Option<String>,
 }
@@ -11,12 +19,124 @@ impl Default for ProfileInfo {
     fn default() -> Self {
         Self {
             fans: vec!["default".to_owned()],
+            fan_roles: Default::default(),
             leds: Default::default(),
             performance_profile: Default::default(),
         }
     }
 }
 
+impl ProfileInfo {
+    /// The fan profile of the fan with the given index and role.
+    /// The role takes precedence over the position.
+    pub fn fan_profile(&self, idx: usize, role: Option<FanRole>) -> Option<&String> {
+        role.and_then(|role| self.fan_roles.get(&role))
+            .or_else(|| self.fans.get(idx))
+    }
+
+    /// The names of all referenced fan profiles.
+    pub fn fan_profiles(&self) -> impl Iterator<Item = &String> {
+        self.fans.iter().chain(self.fan_roles.values())
+    }
+
+    /// Mutable references to all referenced fan profiles.
+    pub fn fan_profiles_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.fans.iter_mut().chain(self.fan_roles.values_mut())
+    }
+}
+
+/// A global profile as it's stored.
+///
+/// A profile with a base inherits every field it doesn't set from
+/// the resolved base profile. Profiles without a base are complete
+/// and fall back to the defaults of [`ProfileInfo`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ProfileDefinition {
+    /// The name of the global profile to inherit from.
+    #[serde(default, alias = "extends", skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fans: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fan_roles: Option<BTreeMap<FanRole, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leds: Option<Vec<LedProfile>>,
+    /// Inherited profiles can't unset the performance profile of their base.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub performance_profile: Option<String>,
+}
+
+impl ProfileDefinition {
+    /// Override the fields of the resolved base profile that are set.
+    pub fn resolve(self, base: ProfileInfo) -> ProfileInfo {
+        ProfileInfo {
+            fans: self.fans.unwrap_or(base.fans),
+            fan_roles: self.fan_roles.unwrap_or(base.fan_roles),
+            leds: self.leds.unwrap_or(base.leds),
+            performance_profile: self.performance_profile.or(base.performance_profile),
+        }
+    }
+
+    /// Set the fields that are inherited from `base`,
+    /// so the profile doesn't depend on it anymore.
+    pub fn detach(&mut self, base: ProfileDefinition) {
+        self.base = base.base;
+        if self.fans.is_none() {
+            self.fans = base.fans;
+        }
+        if self.fan_roles.is_none() {
+            self.fan_roles = base.fan_roles;
+        }
+        if self.leds.is_none() {
+            self.leds = base.leds;
+        }
+        if self.performance_profile.is_none() {
+            self.performance_profile = base.performance_profile;
+        }
+    }
+
+    /// Set the fields that were changed from the `previous` to the `edited`
+    /// resolved profile. Unchanged fields are still inherited from the base.
+    pub fn update(&mut self, previous: &ProfileInfo, edited: ProfileInfo) {
+        if edited.fans != previous.fans {
+            self.fans = Some(edited.fans);
+        }
+        if edited.fan_roles != previous.fan_roles {
+            self.fan_roles = Some(edited.fan_roles);
+        }
+        if edited.leds != previous.leds {
+            self.leds = Some(edited.leds);
+        }
+        if edited.performance_profile != previous.performance_profile {
+            self.performance_profile = edited.performance_profile;
+        }
+    }
+
+    /// The names of the fan profiles that are set in this profile.
+    pub fn fan_profiles(&self) -> impl Iterator<Item = &String> {
+        let fans = self.fans.iter().flatten();
+        fans.chain(self.fan_roles.iter().flat_map(BTreeMap::values))
+    }
+
+    /// Mutable references to the fan profiles that are set in this profile.
+    pub fn fan_profiles_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        let fans = self.fans.iter_mut().flatten();
+        fans.chain(self.fan_roles.iter_mut().flat_map(BTreeMap::values_mut))
+    }
+}
+
+impl From<ProfileInfo> for ProfileDefinition {
+    fn from(info: ProfileInfo) -> Self {
+        Self {
+            base: None,
+            fans: Some(info.fans),
+            fan_roles: Some(info.fan_roles),
+            leds: Some(info.leds),
+            performance_profile: info.performance_profile,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct LedProfile {
     pub device_name: String,
@@ -24,4 +144,137 @@ pub struct LedProfile {
     pub profile: String,
     #[serde(default)]
     pub mode: LedControllerMode,
+    /// Brightness in percent that scales the color profile.
+    /// Full brightness is used if the value isn't set.
+    #[serde(default)]
+    pub brightness: Option<u8>,
+    /// Correct the non-linear brightness of the LEDs.
+    #[serde(default)]
+    pub gamma_correction: bool,
+    /// Delay of the color animation in ms compared to other devices.
+    /// Different offsets create wave effects across devices.
+    #[serde(default)]
+    pub phase_offset: u32,
+}
+
+/// What happens to the global profiles that still reference
+/// a fan or LED profile when it is removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RemovalStrategy {
+    /// Don't remove the profile while it's referenced.
+    #[default]
+    Refuse,
+    /// Remove the global profiles that reference the profile as well.
+    /// Global profiles that are active, inherited from by other profiles
+    /// or activated by schedules and rules are never removed.
+    Cascade,
+    /// Reference the given profile instead.
+    Replace(String),
+}
+
+/// The layer a profile is stored in.
+/// Profiles of higher layers shadow profiles with the same name in lower layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ProfileOrigin {
+    /// Read-only defaults shipped by the distribution.
+    Vendor,
+    /// Profiles managed by the administrator.
+    System,
+    /// Profiles owned by the user with the given UID.
+    User(u32),
+}
+
+/// A profile name together with the layer it's loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub origin: ProfileOrigin,
+}
+
+/// When a temporarily activated global profile
+/// is replaced by the previous profile again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RevertCondition {
+    /// After the given number of seconds.
+    After(u64),
+    /// When the system resumes from suspend.
+    Resume,
+    /// When the AC adapter is plugged in or removed.
+    AcChange,
+}
+
+/// A global profile that is active until a condition is met.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TemporaryProfile {
+    pub name: String,
+    pub until: RevertCondition,
+    /// The seconds until the previous profile is activated again
+    /// if the condition is [`RevertCondition::After`].
+    pub remaining: Option<u64>,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::FanRole;
+
+    use super::{ProfileDefinition, ProfileInfo};
+
+    #[test]
+    fn fan_roles_take_precedence() {
+        let info: ProfileInfo = serde_json::from_str(
+            r#"{ "fans": ["silent", "quiet"], "fan_roles": { "gpu": "performance" },
+                 "leds": [], "performance_profile": null }"#,
+        )
+        .unwrap();
+        let fan = |idx, role| info.fan_profile(idx, role).map(String::as_str);
+
+        assert_eq!(fan(0, Some(FanRole::Cpu)), Some("silent"));
+        assert_eq!(fan(1, Some(FanRole::Gpu)), Some("performance"));
+        // Fans without a role and profiles without roles use the position.
+        assert_eq!(fan(1, None), Some("quiet"));
+        assert_eq!(fan(2, None), None);
+        assert_eq!(info.fan_profiles().count(), 3);
+    }
+
+    #[test]
+    fn inherit_from_base() {
+        let base = ProfileDefinition {
+            base: None,
+            fans: Some(vec!["silent".to_owned(), "silent".to_owned()]),
+            fan_roles: None,
+            leds: None,
+            performance_profile: Some("power_save".to_owned()),
+        };
+        let mut derived: ProfileDefinition =
+            serde_json::from_str(r#"{ "extends": "quiet", "performance_profile": "performance" }"#)
+                .unwrap();
+        assert_eq!(derived.base.as_deref(), Some("quiet"));
+
+        let resolved = derived
+            .clone()
+            .resolve(base.clone().resolve(ProfileInfo::default()));
+        assert_eq!(resolved.fans, ["silent", "silent"]);
+        assert!(resolved.leds.is_empty());
+        assert_eq!(resolved.performance_profile.as_deref(), Some("performance"));
+
+        let mut edited = derived.clone();
+        edited.update(
+            &resolved,
+            ProfileInfo {
+                fans: vec!["loud".to_owned()],
+                ..resolved.clone()
+            },
+        );
+        assert_eq!(edited.base.as_deref(), Some("quiet"));
+        assert_eq!(edited.fans, Some(vec!["loud".to_owned()]));
+        assert_eq!(edited.leds, None);
+
+        derived.detach(base);
+        assert_eq!(derived.base, None);
+        assert_eq!(derived.clone().resolve(ProfileInfo::default()), resolved);
+        assert_eq!(
+            serde_json::to_string(&derived).unwrap(),
+            r#"{"fans":["silent","silent"],"performance_profile":"performance"}"#
+        );
+    }
 }
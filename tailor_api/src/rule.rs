@@ -0,0 +1,65 @@
+/// Activates a global profile while a matching process is running.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProcessRule {
+    /// The name of the global profile.
+    pub profile: String,
+    /// A single word matches the name of the executable, like `steam`.
+    /// Multiple words match a part of the command line, like `cargo build`.
+    pub command: String,
+    /// If multiple rules match, the rule with the highest priority wins.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl ProcessRule {
+    /// Check a process with the arguments of its command line.
+    pub fn matches(&self, args: &[String]) -> bool {
+        let command = self.command.trim();
+        if command.contains(char::is_whitespace) {
+            let words: Vec<&str> = command.split_whitespace().collect();
+            args.windows(words.len()).any(|window| {
+                window
+                    .iter()
+                    .zip(&words)
+                    .enumerate()
+                    .all(|(idx, (arg, word))| {
+                        // The first word is an executable that might be started with its path.
+                        if idx == 0 {
+                            executable_name(arg) == *word
+                        } else {
+                            arg == word
+                        }
+                    })
+            })
+        } else {
+            args.first()
+                .is_some_and(|arg| executable_name(arg) == command)
+        }
+    }
+}
+
+fn executable_name(arg: &str) -> &str {
+    arg.rsplit('/').next().unwrap_or(arg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProcessRule;
+
+    #[test]
+    fn match_processes() {
+        let rule = |command: &str| ProcessRule {
+            profile: "performance".to_owned(),
+            command: command.to_owned(),
+            priority: 0,
+        };
+        let args = |line: &str| -> Vec<String> { line.split(' ').map(ToOwned::to_owned).collect() };
+
+        assert!(rule("steam").matches(&args("/usr/bin/steam -silent")));
+        assert!(!rule("steam").matches(&args("/usr/bin/steamwebhelper")));
+        assert!(!rule("steam").matches(&args("vim steam")));
+        assert!(rule("cargo build").matches(&args("/home/user/.cargo/bin/cargo build --release")));
+        assert!(!rule("cargo build").matches(&args("cargo test")));
+        assert!(!rule("cargo build").matches(&[]));
+    }
+}
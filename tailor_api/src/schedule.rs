@@ -0,0 +1,191 @@
+use std::{fmt::Display, str::FromStr};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A time of the day in the local time zone, written as `HH:MM`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeOfDay {
+    hour: u8,
+    minute: u8,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: u8, minute: u8) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self { hour, minute })
+    }
+
+    pub fn hour(self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(self) -> u8 {
+        self.minute
+    }
+
+    /// The minutes since midnight.
+    pub fn minutes(self) -> u32 {
+        self.hour as u32 * 60 + self.minute as u32
+    }
+}
+
+impl Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time `{s}`, expected HH:MM");
+        let (hour, minute) = s.split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        Self::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+impl serde::Serialize for TimeOfDay {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TimeOfDay {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// The day for the number of days since Sunday, like `tm_wday` of libc.
+    pub fn from_days_since_sunday(days: u32) -> Self {
+        match days % 7 {
+            0 => Weekday::Sunday,
+            1 => Weekday::Monday,
+            2 => Weekday::Tuesday,
+            3 => Weekday::Wednesday,
+            4 => Weekday::Thursday,
+            5 => Weekday::Friday,
+            _ => Weekday::Saturday,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Weekday::Monday => Weekday::Sunday,
+            Weekday::Tuesday => Weekday::Monday,
+            Weekday::Wednesday => Weekday::Tuesday,
+            Weekday::Thursday => Weekday::Wednesday,
+            Weekday::Friday => Weekday::Thursday,
+            Weekday::Saturday => Weekday::Friday,
+            Weekday::Sunday => Weekday::Saturday,
+        }
+    }
+}
+
+/// Activates a global profile during a time window.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProfileSchedule {
+    /// The name of the global profile.
+    pub profile: String,
+    pub start: TimeOfDay,
+    /// Windows with an end before the start continue on the next day.
+    /// Windows with the same start and end last a whole day.
+    pub end: TimeOfDay,
+    /// The days on which the window starts. Empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+}
+
+impl ProfileSchedule {
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn duration(&self) -> u32 {
+        match (MINUTES_PER_DAY + self.end.minutes() - self.start.minutes()) % MINUTES_PER_DAY {
+            0 => MINUTES_PER_DAY,
+            duration => duration,
+        }
+    }
+
+    /// If the window includes the given time, returns how many minutes ago it started.
+    /// If multiple schedules are active, the one that started last takes precedence.
+    pub fn active_since(&self, day: Weekday, time: TimeOfDay) -> Option<u32> {
+        let duration = self.duration();
+        let now = time.minutes();
+        let start = self.start.minutes();
+
+        if now >= start && now - start < duration && self.starts_on(day) {
+            Some(now - start)
+        } else {
+            // The window might have started on the previous day.
+            let elapsed = MINUTES_PER_DAY + now - start;
+            (elapsed < duration && self.starts_on(day.previous())).then_some(elapsed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ProfileSchedule, TimeOfDay, Weekday};
+
+    fn time(s: &str) -> TimeOfDay {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn schedule_windows() {
+        let night = ProfileSchedule {
+            profile: "quiet".to_owned(),
+            start: time("22:00"),
+            end: time("07:00"),
+            days: Vec::new(),
+        };
+        assert_eq!(night.active_since(Weekday::Monday, time("23:30")), Some(90));
+        assert_eq!(
+            night.active_since(Weekday::Tuesday, time("06:59")),
+            Some(539)
+        );
+        assert_eq!(night.active_since(Weekday::Tuesday, time("07:00")), None);
+        assert_eq!(night.active_since(Weekday::Tuesday, time("21:59")), None);
+
+        let office = ProfileSchedule {
+            profile: "performance".to_owned(),
+            start: time("09:00"),
+            end: time("17:00"),
+            days: vec![Weekday::Monday, Weekday::Friday],
+        };
+        assert_eq!(office.active_since(Weekday::Friday, time("09:00")), Some(0));
+        assert_eq!(office.active_since(Weekday::Saturday, time("10:00")), None);
+
+        let weekend = ProfileSchedule {
+            profile: "gaming".to_owned(),
+            start: time("00:00"),
+            end: time("00:00"),
+            days: vec![Weekday::Saturday],
+        };
+        assert_eq!(
+            weekend.active_since(Weekday::Saturday, time("23:59")),
+            Some(1439)
+        );
+        assert_eq!(weekend.active_since(Weekday::Sunday, time("00:00")), None);
+
+        assert_eq!(time("7:05").to_string(), "07:05");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{ColorProfile, FanProfilePoint, ProfileDefinition};
+
+/// The version of the format of stored profile files.
+///
+/// Stored files wrap the profile as `{ "version": 1, "profile": ... }`.
+/// Files without this wrapper are treated as version 0.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades a profile by one version.
+type Migration = fn(ProfileKind, Value) -> Result<Value, SchemaError>;
+
+/// The index of each migration is the version of its input.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [migrate_v0];
+
+/// The type of a stored profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProfileKind {
+    /// A [`ProfileDefinition`].
+    Global,
+    /// A [`ColorProfile`].
+    Led,
+    /// A list of [`FanProfilePoint`].
+    Fan,
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Json(serde_json::Error),
+    /// The file was written by a newer version.
+    UnsupportedVersion(u32),
+    Invalid(String),
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "Invalid JSON: {err}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported schema version {version}, the latest supported version is {SCHEMA_VERSION}"
+            ),
+            Self::Invalid(msg) => write!(f, "Invalid profile: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// A profile in the latest format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradedProfile {
+    /// The schema version of the input.
+    pub from_version: u32,
+    /// The validated profile without the version wrapper.
+    pub profile: Value,
+}
+
+impl UpgradedProfile {
+    /// Whether the input used an older schema.
+    pub fn was_upgraded(&self) -> bool {
+        self.from_version < SCHEMA_VERSION
+    }
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    version: u32,
+    profile: &'a T,
+}
+
+/// Serialize a profile in the format of stored files.
+pub fn to_versioned_json<T: Serialize>(profile: &T) -> Result<String, SchemaError> {
+    Ok(serde_json::to_string_pretty(&Versioned {
+        version: SCHEMA_VERSION,
+        profile,
+    })?)
+}
+
+/// Validate profile JSON of any supported version and upgrade it to the latest format.
+///
+/// Accepts stored files as well as plain profiles as they are sent over D-Bus.
+pub fn upgrade_profile(kind: ProfileKind, json: &str) -> Result<UpgradedProfile, SchemaError> {
+    let value: Value = serde_json::from_str(json)?;
+    let (from_version, mut profile) = split_version(value)?;
+    if from_version > SCHEMA_VERSION {
+        return Err(SchemaError::UnsupportedVersion(from_version));
+    }
+
+    for migration in &MIGRATIONS[from_version as usize..] {
+        profile = migration(kind, profile)?;
+    }
+
+    // Round trip through the typed profile to validate it and fill in defaults.
+    let profile = match kind {
+        ProfileKind::Global => validate::<ProfileDefinition>(profile)?,
+        ProfileKind::Led => validate::<ColorProfile>(profile)?,
+        ProfileKind::Fan => validate::<Vec<FanProfilePoint>>(profile)?,
+    };
+    Ok(UpgradedProfile {
+        from_version,
+        profile,
+    })
+}
+
+fn validate<T: serde::de::DeserializeOwned + Serialize>(
+    value: Value,
+) -> Result<Value, SchemaError> {
+    let profile: T = serde_json::from_value(value)?;
+    Ok(serde_json::to_value(profile)?)
+}
+
+/// Split the version wrapper from a profile.
+fn split_version(value: Value) -> Result<(u32, Value), SchemaError> {
+    match value {
+        Value::Object(mut map) if map.len() == 2 && map.contains_key("version") => {
+            let version = map
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| SchemaError::Invalid("The version isn't a number".to_owned()))?;
+            let profile = map
+                .remove("profile")
+                .ok_or_else(|| SchemaError::Invalid("Missing profile".to_owned()))?;
+            Ok((version as u32, profile))
+        }
+        value => Ok((0, value)),
+    }
+}
+
+/// Version 0 files are plain profiles. LED entries of global
+/// profiles might lack the mode, which defaults to RGB.
+fn migrate_v0(kind: ProfileKind, mut profile: Value) -> Result<Value, SchemaError> {
+    if kind == ProfileKind::Global {
+        if let Some(leds) = profile.get_mut("leds").and_then(Value::as_array_mut) {
+            for led in leds.iter_mut().filter_map(Value::as_object_mut) {
+                led.entry("mode").or_insert_with(|| "Rgb".into());
+            }
+        }
+    }
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ColorProfile, ProfileInfo};
+
+    use super::{to_versioned_json, upgrade_profile, ProfileKind, SchemaError, SCHEMA_VERSION};
+
+    #[test]
+    fn upgrade_legacy_profiles() {
+        let legacy = r#"{
+            "fans": ["default"],
+            "leds": [{ "device_name": "dev", "function": "kbd_backlight", "profile": "default" }],
+            "performance_profile": null
+        }"#;
+        let upgraded = upgrade_profile(ProfileKind::Global, legacy).unwrap();
+        assert_eq!(upgraded.from_version, 0);
+        assert!(upgraded.was_upgraded());
+        assert_eq!(upgraded.profile["leds"][0]["mode"], "Rgb");
+
+        let stored = to_versioned_json(&ColorProfile::None).unwrap();
+        let upgraded = upgrade_profile(ProfileKind::Led, &stored).unwrap();
+        assert_eq!(upgraded.from_version, SCHEMA_VERSION);
+        assert_eq!(
+            upgraded.profile,
+            serde_json::to_value(ColorProfile::None).unwrap()
+        );
+
+        let future = r#"{ "version": 1000, "profile": [] }"#;
+        assert!(matches!(
+            upgrade_profile(ProfileKind::Fan, future),
+            Err(SchemaError::UnsupportedVersion(1000))
+        ));
+        assert!(upgrade_profile(ProfileKind::Fan, r#"{ "fans": [] }"#).is_err());
+
+        let default = to_versioned_json(&ProfileInfo::default()).unwrap();
+        assert!(upgrade_profile(ProfileKind::Global, &default).is_ok());
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 /// CLI to interact with tailord
@@ -15,6 +17,24 @@ pub(crate) enum Command {
         #[command(subcommand)]
         profile_cmd: ProfileCommand,
     },
+
+    /// LED commands
+    Led {
+        #[command(subcommand)]
+        led_cmd: LedCommand,
+    },
+
+    /// Schedule commands
+    Schedule {
+        #[command(subcommand)]
+        schedule_cmd: ScheduleCommand,
+    },
+
+    /// Process rule commands
+    Rule {
+        #[command(subcommand)]
+        rule_cmd: RuleCommand,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -22,13 +42,31 @@ pub(crate) enum ProfileCommand {
     /// List profile names
     List,
 
-    /// Set the active profile
+    /// Set the active profile, which pauses schedules and rules until unlock
     Set {
         /// The name of the profile to set (see: list)
         #[arg()]
         name: String,
+
+        /// Activate the previous profile again after a duration like 30m, 1h30m or 90s
+        #[arg(long = "for", value_name = "DURATION", group = "temporary")]
+        duration: Option<String>,
+
+        /// Activate the previous profile again after the next suspend
+        #[arg(long, group = "temporary")]
+        until_resume: bool,
+
+        /// Activate the previous profile again when the AC adapter is plugged in or removed
+        #[arg(long, group = "temporary")]
+        until_ac_change: bool,
     },
 
+    /// Let schedules and rules switch the profile again after set
+    Unlock,
+
+    /// End a temporary profile early and activate the previous profile again
+    Restore,
+
     /// Cycle profiles
     Cycle {
         // Print the new profile to stdout
@@ -39,4 +77,188 @@ pub(crate) enum ProfileCommand {
         #[arg(long, short)]
         notify: bool,
     },
+
+    /// Export a profile with all fan and LED profiles it uses
+    Export {
+        /// The name of the profile to export (see: list)
+        #[arg()]
+        name: String,
+
+        /// The file to write the bundle to instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import a profile bundle created by export
+    Import {
+        /// The bundle file
+        #[arg()]
+        file: PathBuf,
+
+        /// What to do with profiles that already exist with a different content
+        #[arg(long, value_enum, default_value_t = OnConflict::Fail)]
+        on_conflict: OnConflict,
+    },
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum OnConflict {
+    /// Abort the import
+    Fail,
+    /// Replace the existing profiles
+    Overwrite,
+    /// Keep the existing profiles
+    Skip,
+    /// Import the profiles with a new name
+    Rename,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum LedCommand {
+    /// Print the current brightness in percent
+    GetBrightness,
+
+    /// Set the brightness in percent
+    SetBrightness {
+        /// The new brightness (0 to 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        brightness: u8,
+
+        /// Store the brightness in the active profile
+        #[arg(long, short)]
+        persist: bool,
+    },
+
+    /// Increase the brightness
+    BrightnessUp {
+        /// The step size in percent
+        #[arg(long, short, default_value_t = 10)]
+        step: u8,
+
+        /// Store the brightness in the active profile
+        #[arg(long, short)]
+        persist: bool,
+    },
+
+    /// Decrease the brightness
+    BrightnessDown {
+        /// The step size in percent
+        #[arg(long, short, default_value_t = 10)]
+        step: u8,
+
+        /// Store the brightness in the active profile
+        #[arg(long, short)]
+        persist: bool,
+    },
+
+    /// Blink a color and continue with the active color profile afterwards
+    Blink {
+        /// The color as hexadecimal value (e.g. ff0000)
+        #[arg()]
+        color: String,
+
+        /// How often the color blinks
+        #[arg(long, short, default_value_t = 3)]
+        count: u32,
+    },
+
+    /// Fade a color in and out and continue with the active color profile afterwards
+    Pulse {
+        /// The color as hexadecimal value (e.g. ff0000)
+        #[arg()]
+        color: String,
+
+        /// The duration in ms
+        #[arg(long, short, default_value_t = 2000)]
+        duration: u32,
+    },
+
+    /// List LED entries of the global profiles that don't match a detected device
+    Unmatched,
+
+    /// Re-target LED entries of the global profiles to the detected devices
+    Migrate {
+        /// Only print the changes without saving them
+        #[arg(long, short)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum ScheduleCommand {
+    /// List the schedules
+    List,
+
+    /// Add or replace a schedule that activates a profile during a time window
+    Add {
+        /// The name of the schedule
+        #[arg()]
+        name: String,
+
+        /// The profile to activate (see: profile list)
+        #[arg(long, short)]
+        profile: String,
+
+        /// The start time as HH:MM
+        #[arg(long, short)]
+        start: String,
+
+        /// The end time as HH:MM, times before the start end on the next day
+        #[arg(long, short)]
+        end: String,
+
+        /// The days on which the schedule starts, every day if none are given
+        #[arg(long, short, value_enum, value_delimiter = ',')]
+        days: Vec<Day>,
+    },
+
+    /// Remove a schedule
+    Remove {
+        /// The name of the schedule
+        #[arg()]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum RuleCommand {
+    /// List the process rules
+    List,
+
+    /// Add or replace a rule that activates a profile while a process runs
+    Add {
+        /// The name of the rule
+        #[arg()]
+        name: String,
+
+        /// The profile to activate (see: profile list)
+        #[arg(long, short)]
+        profile: String,
+
+        /// The name of the executable like `steam`, or a part of the command line like `cargo build`
+        #[arg(long, short)]
+        command: String,
+
+        /// Rules with a higher priority win if multiple processes match
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i32,
+    },
+
+    /// Remove a process rule
+    Remove {
+        /// The name of the rule
+        #[arg()]
+        name: String,
+    },
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
 }
@@ -0,0 +1,121 @@
+use eyre::Result;
+use tailor_api::{match_led_profiles, Color, FlashPattern, LedMatch};
+use tailor_client::TailorConnection;
+
+use crate::cli::LedCommand;
+
+/// Handle LED commands
+pub(crate) async fn handle(cmd: LedCommand) -> Result<()> {
+    let connection = TailorConnection::new().await?;
+    match cmd {
+        LedCommand::GetBrightness => {
+            println!("{}", connection.get_led_brightness().await?);
+        }
+        LedCommand::SetBrightness {
+            brightness,
+            persist,
+        } => {
+            set_brightness(&connection, brightness, persist).await?;
+        }
+        LedCommand::BrightnessUp { step, persist } => {
+            let brightness = connection.get_led_brightness().await?;
+            let brightness = brightness.saturating_add(step).min(100);
+            set_brightness(&connection, brightness, persist).await?;
+        }
+        LedCommand::BrightnessDown { step, persist } => {
+            let brightness = connection.get_led_brightness().await?;
+            let brightness = brightness.saturating_sub(step);
+            set_brightness(&connection, brightness, persist).await?;
+        }
+        LedCommand::Blink { color, count } => {
+            let color: Color = color.parse()?;
+            connection
+                .flash_leds(&FlashPattern::blink(color, count))
+                .await?;
+        }
+        LedCommand::Pulse { color, duration } => {
+            let color: Color = color.parse()?;
+            connection
+                .flash_leds(&FlashPattern::pulse(color, duration))
+                .await?;
+        }
+        LedCommand::Unmatched => {
+            for (profile, entry) in connection.list_unmatched_led_entries().await? {
+                println!("{profile}: {}::{}", entry.device_name, entry.function);
+            }
+        }
+        LedCommand::Migrate { dry_run } => {
+            migrate(&connection, dry_run).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-target the LED entries that only match a device by function or mode.
+/// Inherited entries are migrated together with the base profile that sets them.
+async fn migrate(connection: &TailorConnection<'_>, dry_run: bool) -> Result<()> {
+    let devices = connection.get_led_devices().await?;
+
+    for name in connection.list_global_profiles().await? {
+        let mut profile = connection.get_global_profile_definition(&name).await?;
+        let Some(leds) = &mut profile.leds else {
+            continue;
+        };
+        let mut changed = false;
+
+        for (device, assignment) in devices.iter().zip(match_led_profiles(leds, &devices)) {
+            let Some((idx, quality)) = assignment else {
+                continue;
+            };
+            if quality == LedMatch::Exact {
+                continue;
+            }
+
+            let entry = &mut leds[idx];
+            println!(
+                "{name}: {}::{} -> {} ({quality:?} match)",
+                entry.device_name,
+                entry.function,
+                device.device_id(),
+            );
+            entry.device_name = device.device_name.clone();
+            entry.function = device.function.clone();
+            entry.mode = device.mode;
+            changed = true;
+        }
+
+        if changed && !dry_run {
+            connection
+                .add_global_profile_definition(&name, &profile)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn set_brightness(
+    connection: &TailorConnection<'_>,
+    brightness: u8,
+    persist: bool,
+) -> Result<()> {
+    connection.set_led_brightness(brightness).await?;
+
+    if persist {
+        let name = connection.get_active_global_profile_name().await?;
+        let mut profile = connection.get_global_profile_definition(&name).await?;
+        let leds = match profile.leds.take() {
+            Some(leds) => leds,
+            // Changing the base profile would affect other profiles as well,
+            // so the inherited entries are overridden in the active profile.
+            None => connection.get_global_profile(&name).await?.leds,
+        };
+        let leds = profile.leds.insert(leds);
+        for led in leds {
+            led.brightness = Some(brightness);
+        }
+        connection
+            .add_global_profile_definition(&name, &profile)
+            .await?;
+    }
+    Ok(())
+}
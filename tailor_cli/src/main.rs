@@ -1,5 +1,8 @@
 mod cli;
+mod led;
 mod profile;
+mod rule;
+mod schedule;
 
 use clap::Parser;
 use eyre::Result;
@@ -9,8 +12,12 @@ use crate::cli::{Command, Opts};
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Opts::parse();
-    if let Some(Command::Profile { profile_cmd }) = args.command {
-        profile::handle(profile_cmd).await?;
+    match args.command {
+        Some(Command::Profile { profile_cmd }) => profile::handle(profile_cmd).await?,
+        Some(Command::Led { led_cmd }) => led::handle(led_cmd).await?,
+        Some(Command::Schedule { schedule_cmd }) => schedule::handle(schedule_cmd).await?,
+        Some(Command::Rule { rule_cmd }) => rule::handle(rule_cmd).await?,
+        None => {}
     }
     Ok(())
 }
@@ -1,8 +1,9 @@
 use colored::Colorize;
-use eyre::Result;
+use eyre::{eyre, Result};
+use tailor_api::{ConflictPolicy, ProfileBundle, ProfileOrigin, RevertCondition, TemporaryProfile};
 use tailor_client::TailorConnection;
 
-use crate::cli::ProfileCommand;
+use crate::cli::{OnConflict, ProfileCommand};
 use notify_rust::Notification;
 
 /// Handle profile commands
@@ -12,17 +13,56 @@ pub(crate) async fn handle(cmd: ProfileCommand) -> Result<()> {
         ProfileCommand::List => {
             let active_profile = connection.get_active_global_profile_name().await?;
             let inactive_profiles: Vec<String> = connection
-                .list_global_profiles()
+                .list_global_profile_entries()
                 .await?
                 .into_iter()
-                .filter(|name| name != &active_profile)
+                .filter(|entry| entry.name != active_profile)
+                .map(|entry| match entry.origin {
+                    ProfileOrigin::System => entry.name,
+                    ProfileOrigin::Vendor => format!("{} (vendor)", entry.name),
+                    ProfileOrigin::User(_) => format!("{} (user)", entry.name),
+                })
                 .collect();
-            let active_profile_str = format!("{} (active)", active_profile).bold().green();
+            let active_profile_str = match connection.get_temporary_profile().await? {
+                Some(temporary) if temporary.name == active_profile => {
+                    format!("{} (active {})", active_profile, describe(&temporary))
+                }
+                _ => format!("{} (active)", active_profile),
+            }
+            .bold()
+            .green();
             println!("{}\n{}", active_profile_str, inactive_profiles.join("\n"));
         }
-        ProfileCommand::Set { name } => {
-            connection.set_active_global_profile_name(&name).await?;
-            connection.reload().await?;
+        ProfileCommand::Set {
+            name,
+            duration,
+            until_resume,
+            until_ac_change,
+        } => {
+            let until = if let Some(duration) = duration {
+                Some(RevertCondition::After(parse_duration(&duration)?))
+            } else if until_resume {
+                Some(RevertCondition::Resume)
+            } else if until_ac_change {
+                Some(RevertCondition::AcChange)
+            } else {
+                None
+            };
+
+            if let Some(until) = until {
+                connection
+                    .activate_global_profile_temporarily(&name, until)
+                    .await?;
+            } else {
+                connection.set_active_global_profile_name(&name).await?;
+                connection.reload().await?;
+            }
+        }
+        ProfileCommand::Unlock => {
+            connection.clear_profile_lock().await?;
+        }
+        ProfileCommand::Restore => {
+            connection.cancel_temporary_profile().await?;
         }
         ProfileCommand::Cycle { verbose, notify } => {
             let active_profile = connection.get_active_global_profile_name().await?;
@@ -53,6 +93,72 @@ pub(crate) async fn handle(cmd: ProfileCommand) -> Result<()> {
                 }
             }
         }
+        ProfileCommand::Export { name, output } => {
+            let bundle = connection.export_bundle(&name).await?;
+            let data = serde_json::to_string_pretty(&bundle)?;
+            if let Some(output) = output {
+                std::fs::write(output, data)?;
+            } else {
+                println!("{data}");
+            }
+        }
+        ProfileCommand::Import { file, on_conflict } => {
+            let bundle: ProfileBundle = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+            let policy = match on_conflict {
+                OnConflict::Fail => ConflictPolicy::Fail,
+                OnConflict::Overwrite => ConflictPolicy::Overwrite,
+                OnConflict::Skip => ConflictPolicy::Skip,
+                OnConflict::Rename => ConflictPolicy::Rename,
+            };
+            let name = connection.import_bundle(&bundle, policy).await?;
+            println!("Imported profile `{name}`");
+        }
     }
     Ok(())
 }
+
+/// Parse durations like `30m`, `1h30m` or `90s`. Plain numbers are minutes.
+fn parse_duration(value: &str) -> Result<u64> {
+    let invalid = || eyre!("Invalid duration `{value}`, expected something like 30m or 1h30m");
+    if let Ok(minutes) = value.parse::<u64>() {
+        return minutes.checked_mul(60).ok_or_else(invalid);
+    }
+
+    let mut secs: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let amount: u64 = number.parse().map_err(|_| invalid())?;
+        secs = amount
+            .checked_mul(unit)
+            .and_then(|amount| secs.checked_add(amount))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || secs == 0 {
+        return Err(invalid());
+    }
+    Ok(secs)
+}
+
+fn describe(temporary: &TemporaryProfile) -> String {
+    match (temporary.until, temporary.remaining) {
+        (RevertCondition::After(_), Some(remaining)) => {
+            let minutes = (remaining + 59) / 60;
+            format!("for {}h{:02}m", minutes / 60, minutes % 60)
+        }
+        (RevertCondition::After(_), None) => "for a limited time".to_owned(),
+        (RevertCondition::Resume, _) => "until resume".to_owned(),
+        (RevertCondition::AcChange, _) => "until the AC adapter changes".to_owned(),
+    }
+}
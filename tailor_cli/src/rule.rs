@@ -0,0 +1,47 @@
+use eyre::Result;
+use tailor_api::ProcessRule;
+use tailor_client::TailorConnection;
+
+use crate::cli::RuleCommand;
+
+/// Handle process rule commands
+pub(crate) async fn handle(cmd: RuleCommand) -> Result<()> {
+    let connection = TailorConnection::new().await?;
+    match cmd {
+        RuleCommand::List => {
+            let active = connection.get_active_rule().await?;
+            for name in connection.list_rules().await? {
+                let rule = connection.get_rule(&name).await?;
+                let marker = if active.as_ref() == Some(&name) {
+                    " (active)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{name}{marker}: `{}` while `{}` runs, priority {}",
+                    rule.profile, rule.command, rule.priority
+                );
+            }
+            if connection.is_active_profile_locked().await? {
+                println!("Rules are paused until `tailor profile unlock`");
+            }
+        }
+        RuleCommand::Add {
+            name,
+            profile,
+            command,
+            priority,
+        } => {
+            let rule = ProcessRule {
+                profile,
+                command,
+                priority,
+            };
+            connection.add_rule(&name, &rule).await?;
+        }
+        RuleCommand::Remove { name } => {
+            connection.remove_rule(&name).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use eyre::{eyre, Result};
+use tailor_api::{ProfileSchedule, TimeOfDay, Weekday};
+use tailor_client::TailorConnection;
+
+use crate::cli::{Day, ScheduleCommand};
+
+/// Handle schedule commands
+pub(crate) async fn handle(cmd: ScheduleCommand) -> Result<()> {
+    let connection = TailorConnection::new().await?;
+    match cmd {
+        ScheduleCommand::List => {
+            let active = connection.get_active_schedule().await?;
+            for name in connection.list_schedules().await? {
+                let schedule = connection.get_schedule(&name).await?;
+                let days = if schedule.days.is_empty() {
+                    "every day".to_owned()
+                } else {
+                    format!("{:?}", schedule.days)
+                };
+                let marker = if active.as_ref() == Some(&name) {
+                    " (active)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{name}{marker}: `{}` from {} to {}, {days}",
+                    schedule.profile, schedule.start, schedule.end
+                );
+            }
+            if connection.is_active_profile_locked().await? {
+                println!("Schedules are paused until `tailor profile unlock`");
+            }
+        }
+        ScheduleCommand::Add {
+            name,
+            profile,
+            start,
+            end,
+            days,
+        } => {
+            let schedule = ProfileSchedule {
+                profile,
+                start: parse_time(&start)?,
+                end: parse_time(&end)?,
+                days: days.into_iter().map(weekday).collect(),
+            };
+            connection.add_schedule(&name, &schedule).await?;
+        }
+        ScheduleCommand::Remove { name } => {
+            connection.remove_schedule(&name).await?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_time(time: &str) -> Result<TimeOfDay> {
+    time.parse().map_err(|err: String| eyre!(err))
+}
+
+fn weekday(day: Day) -> Weekday {
+    match day {
+        Day::Mon => Weekday::Monday,
+        Day::Tue => Weekday::Tuesday,
+        Day::Wed => Weekday::Wednesday,
+        Day::Thu => Weekday::Thursday,
+        Day::Fri => Weekday::Friday,
+        Day::Sat => Weekday::Saturday,
+        Day::Sun => Weekday::Sunday,
+    }
+}
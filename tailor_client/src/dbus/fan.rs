@@ -12,9 +12,20 @@ pub trait Fan {
 
     async fn list_profiles(&self) -> fdo::Result<Vec<String>>;
 
+    async fn list_profile_entries(&self) -> fdo::Result<String>;
+
+    #[zbus(signal)]
+    fn profile_changed(&self, name: &str) -> fdo::Result<()>;
+
     async fn remove_profile(&self, name: &str) -> fdo::Result<()>;
 
+    async fn remove_profile_with(&self, name: &str, strategy: &str) -> fdo::Result<()>;
+
+    async fn list_dependents(&self, name: &str) -> fdo::Result<Vec<String>>;
+
     async fn rename_profile(&self, from: &str, to: &str) -> fdo::Result<Vec<String>>;
 
     async fn override_speed(&self, fan_idx: u8, speed: u8) -> fdo::Result<()>;
+
+    async fn override_speed_by_role(&self, role: &str, speed: u8) -> fdo::Result<()>;
 }
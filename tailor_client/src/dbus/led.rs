@@ -12,9 +12,33 @@ pub trait Led {
 
     async fn list_profiles(&self) -> fdo::Result<Vec<String>>;
 
+    async fn list_profile_entries(&self) -> fdo::Result<String>;
+
     async fn remove_profile(&self, name: &str) -> fdo::Result<()>;
 
+    async fn remove_profile_with(&self, name: &str, strategy: &str) -> fdo::Result<()>;
+
+    async fn list_dependents(&self, name: &str) -> fdo::Result<Vec<String>>;
+
     async fn rename_profile(&self, from: &str, to: &str) -> fdo::Result<Vec<String>>;
 
+    async fn set_brightness(&self, brightness: u8) -> fdo::Result<()>;
+
+    async fn get_brightness(&self) -> fdo::Result<u8>;
+
+    #[zbus(signal)]
+    fn brightness_changed(&self, brightness: u8) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn device_added(&self, device: &str) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn device_removed(&self, device: &str) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn profile_changed(&self, name: &str) -> fdo::Result<()>;
+
     async fn override_color(&self, color: &str) -> fdo::Result<()>;
+
+    async fn flash(&self, pattern: &str) -> fdo::Result<()>;
 }
@@ -2,8 +2,12 @@ mod fan;
 mod led;
 mod performance;
 mod profiles;
+mod rules;
+mod schedule;
 
 pub(crate) use fan::FanProxy;
 pub(crate) use led::LedProxy;
 pub(crate) use performance::PerformanceProxy;
 pub(crate) use profiles::ProfilesProxy;
+pub(crate) use rules::RulesProxy;
+pub(crate) use schedule::ScheduleProxy;
@@ -10,8 +10,12 @@ pub trait Profiles {
 
     async fn get_profile(&self, name: &str) -> fdo::Result<String>;
 
+    async fn get_profile_definition(&self, name: &str) -> fdo::Result<String>;
+
     async fn list_profiles(&self) -> fdo::Result<Vec<String>>;
 
+    async fn list_profile_entries(&self) -> fdo::Result<String>;
+
     async fn remove_profile(&self, name: &str) -> fdo::Result<()>;
 
     async fn rename_profile(&self, from: &str, to: &str) -> fdo::Result<Vec<String>>;
@@ -20,9 +24,41 @@ pub trait Profiles {
 
     async fn get_active_profile_name(&self) -> fdo::Result<String>;
 
+    async fn is_active_profile_locked(&self) -> fdo::Result<bool>;
+
+    async fn clear_profile_lock(&self) -> fdo::Result<()>;
+
+    async fn activate_profile_temporarily(&self, name: &str, until: &str) -> fdo::Result<()>;
+
+    async fn get_temporary_profile(&self) -> fdo::Result<String>;
+
+    async fn cancel_temporary_profile(&self) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn temporary_profile_countdown(&self, name: &str, remaining: u64) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn temporary_profile_ended(&self, name: &str) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn profile_changed(&self, name: &str) -> fdo::Result<()>;
+
+    #[zbus(signal)]
+    fn active_profile_changed(&self, name: &str) -> fdo::Result<()>;
+
     async fn get_number_of_fans(&self) -> fdo::Result<u8>;
 
+    async fn get_fan_roles(&self) -> fdo::Result<String>;
+
     async fn get_led_devices(&self) -> fdo::Result<String>;
 
+    async fn list_unmatched_led_entries(&self) -> fdo::Result<String>;
+
+    async fn export_bundle(&self, name: &str) -> fdo::Result<String>;
+
+    async fn import_bundle(&self, data: &str, conflict_policy: &str) -> fdo::Result<String>;
+
     async fn reload(&self) -> fdo::Result<()>;
+
+    async fn reload_config(&self) -> fdo::Result<()>;
 }
@@ -0,0 +1,18 @@
+use zbus::{fdo, proxy};
+
+#[proxy(
+    interface = "com.tux.Tailor.Rules",
+    default_service = "com.tux.Tailor",
+    default_path = "/com/tux/Tailor"
+)]
+pub trait Rules {
+    async fn add_rule(&self, name: &str, value: &str) -> fdo::Result<()>;
+
+    async fn get_rule(&self, name: &str) -> fdo::Result<String>;
+
+    async fn list_rules(&self) -> fdo::Result<Vec<String>>;
+
+    async fn remove_rule(&self, name: &str) -> fdo::Result<()>;
+
+    async fn get_active_rule(&self) -> fdo::Result<String>;
+}
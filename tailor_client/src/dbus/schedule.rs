@@ -0,0 +1,18 @@
+use zbus::{fdo, proxy};
+
+#[proxy(
+    interface = "com.tux.Tailor.Schedule",
+    default_service = "com.tux.Tailor",
+    default_path = "/com/tux/Tailor"
+)]
+pub trait Schedule {
+    async fn add_schedule(&self, name: &str, value: &str) -> fdo::Result<()>;
+
+    async fn get_schedule(&self, name: &str) -> fdo::Result<String>;
+
+    async fn list_schedules(&self) -> fdo::Result<Vec<String>>;
+
+    async fn remove_schedule(&self, name: &str) -> fdo::Result<()>;
+
+    async fn get_active_schedule(&self) -> fdo::Result<String>;
+}
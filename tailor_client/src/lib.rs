@@ -4,8 +4,13 @@ mod dbus;
 mod error;
 
 pub use error::ClientError;
-use tailor_api::{Color, ColorProfile, FanProfilePoint, LedDeviceInfo, ProfileInfo};
-use zbus::Connection;
+use futures_lite::{Stream, StreamExt};
+use tailor_api::{
+    Color, ColorProfile, ConflictPolicy, FanProfilePoint, FanRole, FlashPattern, LedDeviceInfo,
+    LedProfile, ProcessRule, ProfileBundle, ProfileDefinition, ProfileEntry, ProfileInfo,
+    ProfileSchedule, RemovalStrategy, RevertCondition, TemporaryProfile,
+};
+use zbus::{fdo, Connection};
 
 pub type ClientResult<T> = Result<T, ClientError>;
 
@@ -15,6 +20,8 @@ pub struct TailorConnection<'a> {
     led: dbus::LedProxy<'a>,
     fan: dbus::FanProxy<'a>,
     performance: dbus::PerformanceProxy<'a>,
+    schedule: dbus::ScheduleProxy<'a>,
+    rules: dbus::RulesProxy<'a>,
 }
 
 impl<'a> TailorConnection<'a> {
@@ -25,12 +32,16 @@ impl<'a> TailorConnection<'a> {
         let keyboard = dbus::LedProxy::new(&connection).await?;
         let fan = dbus::FanProxy::new(&connection).await?;
         let performance = dbus::PerformanceProxy::new(&connection).await?;
+        let schedule = dbus::ScheduleProxy::new(&connection).await?;
+        let rules = dbus::RulesProxy::new(&connection).await?;
 
         Ok(Self {
             profiles,
             led: keyboard,
             fan,
             performance,
+            schedule,
+            rules,
         })
     }
 }
@@ -50,6 +61,12 @@ impl<'a> TailorConnection<'a> {
         Ok(self.led.list_profiles().await?)
     }
 
+    /// The visible profiles together with the layer they are loaded from.
+    pub async fn list_led_profile_entries(&self) -> ClientResult<Vec<ProfileEntry>> {
+        let data = self.led.list_profile_entries().await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
     pub async fn copy_led_profile(&self, from: &str, to: &str) -> ClientResult<()> {
         let profile = self.get_led_profile(from).await?;
         self.add_led_profile(to, &profile).await
@@ -59,14 +76,100 @@ impl<'a> TailorConnection<'a> {
         Ok(self.led.rename_profile(from, to).await?)
     }
 
+    /// Fails if a global profile still uses the profile,
+    /// see [`TailorConnection::remove_led_profile_with`].
     pub async fn remove_led_profile(&self, name: &str) -> ClientResult<()> {
         Ok(self.led.remove_profile(name).await?)
     }
 
+    /// Remove the profile and update the global profiles that use it.
+    pub async fn remove_led_profile_with(
+        &self,
+        name: &str,
+        strategy: &RemovalStrategy,
+    ) -> ClientResult<()> {
+        let strategy = serde_json::to_string(strategy)?;
+        Ok(self.led.remove_profile_with(name, &strategy).await?)
+    }
+
+    /// The names of the global profiles that use the profile.
+    pub async fn list_led_profile_dependents(&self, name: &str) -> ClientResult<Vec<String>> {
+        Ok(self.led.list_dependents(name).await?)
+    }
+
+    /// Set the brightness of all LED devices in percent (0 to 100).
+    /// This doesn't change the active profile.
+    pub async fn set_led_brightness(&self, brightness: u8) -> ClientResult<()> {
+        Ok(self.led.set_brightness(brightness).await?)
+    }
+
+    /// Read the LED brightness in percent.
+    pub async fn get_led_brightness(&self) -> ClientResult<u8> {
+        Ok(self.led.get_brightness().await?)
+    }
+
+    /// Receive the LED brightness in percent whenever it changes,
+    /// including changes made by the firmware (e.g. brightness hotkeys).
+    pub async fn receive_led_brightness_changes(&self) -> ClientResult<impl Stream<Item = u8>> {
+        let stream = self
+            .led
+            .receive_brightness_changed()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| signal.args().ok().map(|args| args.brightness)))
+    }
+
+    /// Receive LED devices that are plugged in while the daemon is running.
+    pub async fn receive_led_devices_added(
+        &self,
+    ) -> ClientResult<impl Stream<Item = LedDeviceInfo>> {
+        let stream = self
+            .led
+            .receive_device_added()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| {
+            let args = signal.args().ok()?;
+            serde_json::from_str(args.device).ok()
+        }))
+    }
+
+    /// Receive LED devices that are removed while the daemon is running.
+    pub async fn receive_led_devices_removed(
+        &self,
+    ) -> ClientResult<impl Stream<Item = LedDeviceInfo>> {
+        let stream = self
+            .led
+            .receive_device_removed()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| {
+            let args = signal.args().ok()?;
+            serde_json::from_str(args.device).ok()
+        }))
+    }
+
+    /// Receive the names of LED profiles that were changed on disk.
+    pub async fn receive_led_profile_changes(&self) -> ClientResult<impl Stream<Item = String>> {
+        let stream = self
+            .led
+            .receive_profile_changed()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| signal.args().ok().map(|args| args.name.to_owned())))
+    }
+
     pub async fn override_led_colors(&self, color: &Color) -> ClientResult<()> {
         let value = serde_json::to_string(color)?;
         Ok(self.led.override_color(&value).await?)
     }
+
+    /// Play a short pattern on all LED devices, for example as a notification.
+    /// Afterwards, the color profiles continue where they were interrupted.
+    pub async fn flash_leds(&self, pattern: &FlashPattern) -> ClientResult<()> {
+        let value = serde_json::to_string(pattern)?;
+        Ok(self.led.flash(&value).await?)
+    }
 }
 
 impl<'a> TailorConnection<'a> {
@@ -88,6 +191,12 @@ impl<'a> TailorConnection<'a> {
         Ok(self.fan.list_profiles().await?)
     }
 
+    /// The visible profiles together with the layer they are loaded from.
+    pub async fn list_fan_profile_entries(&self) -> ClientResult<Vec<ProfileEntry>> {
+        let data = self.fan.list_profile_entries().await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
     pub async fn copy_fan_profile(&self, from: &str, to: &str) -> ClientResult<()> {
         let profile = self.get_fan_profile(from).await?;
         self.add_fan_profile(to, &profile).await
@@ -97,13 +206,48 @@ impl<'a> TailorConnection<'a> {
         Ok(self.fan.rename_profile(from, to).await?)
     }
 
+    /// Fails if a global profile still uses the profile,
+    /// see [`TailorConnection::remove_fan_profile_with`].
     pub async fn remove_fan_profile(&self, name: &str) -> ClientResult<()> {
         Ok(self.fan.remove_profile(name).await?)
     }
 
+    /// Remove the profile and update the global profiles that use it.
+    pub async fn remove_fan_profile_with(
+        &self,
+        name: &str,
+        strategy: &RemovalStrategy,
+    ) -> ClientResult<()> {
+        let strategy = serde_json::to_string(strategy)?;
+        Ok(self.fan.remove_profile_with(name, &strategy).await?)
+    }
+
+    /// The names of the global profiles that use the profile.
+    pub async fn list_fan_profile_dependents(&self, name: &str) -> ClientResult<Vec<String>> {
+        Ok(self.fan.list_dependents(name).await?)
+    }
+
+    /// Receive the names of fan profiles that were changed on disk.
+    pub async fn receive_fan_profile_changes(&self) -> ClientResult<impl Stream<Item = String>> {
+        let stream = self
+            .fan
+            .receive_profile_changed()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| signal.args().ok().map(|args| args.name.to_owned())))
+    }
+
     pub async fn override_fan_speed(&self, fan_idx: u8, speed: u8) -> ClientResult<()> {
         Ok(self.fan.override_speed(fan_idx, speed).await?)
     }
+
+    /// Override the speed of the fan with the given role.
+    pub async fn override_fan_speed_by_role(&self, role: FanRole, speed: u8) -> ClientResult<()> {
+        Ok(self
+            .fan
+            .override_speed_by_role(&role.to_string(), speed)
+            .await?)
+    }
 }
 
 impl<'a> TailorConnection<'a> {
@@ -112,15 +256,41 @@ impl<'a> TailorConnection<'a> {
         Ok(self.profiles.add_profile(name, &value).await?)
     }
 
+    /// The profile with the fields it inherits from its base profiles.
     pub async fn get_global_profile(&self, name: &str) -> ClientResult<ProfileInfo> {
         let profile_data = self.profiles.get_profile(name).await?;
         Ok(serde_json::from_str(&profile_data)?)
     }
 
+    /// Store a profile that only overrides some fields of its base profile.
+    pub async fn add_global_profile_definition(
+        &self,
+        name: &str,
+        definition: &ProfileDefinition,
+    ) -> ClientResult<()> {
+        let value = serde_json::to_string(definition)?;
+        Ok(self.profiles.add_profile(name, &value).await?)
+    }
+
+    /// The profile as it's stored, without the inherited fields.
+    pub async fn get_global_profile_definition(
+        &self,
+        name: &str,
+    ) -> ClientResult<ProfileDefinition> {
+        let profile_data = self.profiles.get_profile_definition(name).await?;
+        Ok(serde_json::from_str(&profile_data)?)
+    }
+
     pub async fn list_global_profiles(&self) -> ClientResult<Vec<String>> {
         Ok(self.profiles.list_profiles().await?)
     }
 
+    /// The visible profiles together with the layer they are loaded from.
+    pub async fn list_global_profile_entries(&self) -> ClientResult<Vec<ProfileEntry>> {
+        let data = self.profiles.list_profile_entries().await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
     pub async fn copy_global_profile(&self, from: &str, to: &str) -> ClientResult<()> {
         let profile = self.get_global_profile(from).await?;
         self.add_global_profile(to, &profile).await
@@ -142,18 +312,137 @@ impl<'a> TailorConnection<'a> {
         Ok(self.profiles.set_active_profile_name(name).await?)
     }
 
+    /// Receive the names of global profiles that were changed on disk.
+    pub async fn receive_global_profile_changes(&self) -> ClientResult<impl Stream<Item = String>> {
+        let stream = self
+            .profiles
+            .receive_profile_changed()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| signal.args().ok().map(|args| args.name.to_owned())))
+    }
+
+    /// Receive the name of the active global profile whenever another profile
+    /// is activated or the active profile is changed on disk.
+    pub async fn receive_active_profile_changes(&self) -> ClientResult<impl Stream<Item = String>> {
+        let stream = self
+            .profiles
+            .receive_active_profile_changed()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| signal.args().ok().map(|args| args.name.to_owned())))
+    }
+
+    /// Whether the active profile was activated manually,
+    /// which pauses the schedules and process rules.
+    pub async fn is_active_profile_locked(&self) -> ClientResult<bool> {
+        Ok(self.profiles.is_active_profile_locked().await?)
+    }
+
+    /// Let schedules and process rules switch the profile again.
+    pub async fn clear_profile_lock(&self) -> ClientResult<()> {
+        Ok(self.profiles.clear_profile_lock().await?)
+    }
+
+    /// Activate a global profile until the condition is met.
+    /// Afterwards, the previous profile is activated again.
+    pub async fn activate_global_profile_temporarily(
+        &self,
+        name: &str,
+        until: RevertCondition,
+    ) -> ClientResult<()> {
+        let until = serde_json::to_string(&until)?;
+        Ok(self
+            .profiles
+            .activate_profile_temporarily(name, &until)
+            .await?)
+    }
+
+    /// The temporarily activated global profile, if any.
+    pub async fn get_temporary_profile(&self) -> ClientResult<Option<TemporaryProfile>> {
+        let data = self.profiles.get_temporary_profile().await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// End the temporary profile early and activate the previous profile again.
+    pub async fn cancel_temporary_profile(&self) -> ClientResult<()> {
+        Ok(self.profiles.cancel_temporary_profile().await?)
+    }
+
+    /// Receive the name of the temporary profile and the remaining seconds once per minute.
+    pub async fn receive_temporary_profile_countdown(
+        &self,
+    ) -> ClientResult<impl Stream<Item = (String, u64)>> {
+        let stream = self
+            .profiles
+            .receive_temporary_profile_countdown()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| {
+            signal
+                .args()
+                .ok()
+                .map(|args| (args.name.to_owned(), args.remaining))
+        }))
+    }
+
+    /// Receive the names of temporary profiles that ended.
+    pub async fn receive_temporary_profile_ends(&self) -> ClientResult<impl Stream<Item = String>> {
+        let stream = self
+            .profiles
+            .receive_temporary_profile_ended()
+            .await
+            .map_err(fdo::Error::from)?;
+        Ok(stream.filter_map(|signal| signal.args().ok().map(|args| args.name.to_owned())))
+    }
+
     pub async fn get_number_of_fans(&self) -> ClientResult<u8> {
         Ok(self.profiles.get_number_of_fans().await?)
     }
 
+    /// The role of each fan in the order of the fans, if it's known.
+    pub async fn get_fan_roles(&self) -> ClientResult<Vec<Option<FanRole>>> {
+        let data = self.profiles.get_fan_roles().await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
     pub async fn get_led_devices(&self) -> ClientResult<Vec<LedDeviceInfo>> {
         let data = self.profiles.get_led_devices().await?;
         Ok(serde_json::from_str(&data)?)
     }
 
+    /// LED entries of the global profiles that don't match a detected
+    /// device exactly, together with the name of their profile.
+    pub async fn list_unmatched_led_entries(&self) -> ClientResult<Vec<(String, LedProfile)>> {
+        let data = self.profiles.list_unmatched_led_entries().await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// Export a global profile together with all fan and LED profiles it references.
+    pub async fn export_bundle(&self, name: &str) -> ClientResult<ProfileBundle> {
+        let data = self.profiles.export_bundle(name).await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// Import a bundle and return the name of the imported global profile.
+    pub async fn import_bundle(
+        &self,
+        bundle: &ProfileBundle,
+        conflict_policy: ConflictPolicy,
+    ) -> ClientResult<String> {
+        let data = serde_json::to_string(bundle)?;
+        let conflict_policy = serde_json::to_string(&conflict_policy)?;
+        Ok(self.profiles.import_bundle(&data, &conflict_policy).await?)
+    }
+
     pub async fn reload(&self) -> ClientResult<()> {
         Ok(self.profiles.reload().await?)
     }
+
+    /// Load the configuration file of the daemon again.
+    pub async fn reload_config(&self) -> ClientResult<()> {
+        Ok(self.profiles.reload_config().await?)
+    }
 }
 
 impl<'a> TailorConnection<'a> {
@@ -173,3 +462,57 @@ impl<'a> TailorConnection<'a> {
         Ok(self.performance.list_profiles().await?)
     }
 }
+
+impl<'a> TailorConnection<'a> {
+    /// Add or replace a schedule that activates a global profile during a time window.
+    pub async fn add_schedule(&self, name: &str, schedule: &ProfileSchedule) -> ClientResult<()> {
+        let value = serde_json::to_string(schedule)?;
+        Ok(self.schedule.add_schedule(name, &value).await?)
+    }
+
+    pub async fn get_schedule(&self, name: &str) -> ClientResult<ProfileSchedule> {
+        let data = self.schedule.get_schedule(name).await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub async fn list_schedules(&self) -> ClientResult<Vec<String>> {
+        Ok(self.schedule.list_schedules().await?)
+    }
+
+    pub async fn remove_schedule(&self, name: &str) -> ClientResult<()> {
+        Ok(self.schedule.remove_schedule(name).await?)
+    }
+
+    /// The schedule that activated the current profile, if any.
+    pub async fn get_active_schedule(&self) -> ClientResult<Option<String>> {
+        let name = self.schedule.get_active_schedule().await?;
+        Ok((!name.is_empty()).then_some(name))
+    }
+}
+
+impl<'a> TailorConnection<'a> {
+    /// Add or replace a rule that activates a global profile while a process runs.
+    pub async fn add_rule(&self, name: &str, rule: &ProcessRule) -> ClientResult<()> {
+        let value = serde_json::to_string(rule)?;
+        Ok(self.rules.add_rule(name, &value).await?)
+    }
+
+    pub async fn get_rule(&self, name: &str) -> ClientResult<ProcessRule> {
+        let data = self.rules.get_rule(name).await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub async fn list_rules(&self) -> ClientResult<Vec<String>> {
+        Ok(self.rules.list_rules().await?)
+    }
+
+    pub async fn remove_rule(&self, name: &str) -> ClientResult<()> {
+        Ok(self.rules.remove_rule(name).await?)
+    }
+
+    /// The rule that activated the current profile, if any.
+    pub async fn get_active_rule(&self) -> ClientResult<Option<String>> {
+        let name = self.rules.get_active_rule().await?;
+        Ok((!name.is_empty()).then_some(name))
+    }
+}
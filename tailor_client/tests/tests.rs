@@ -1,4 +1,6 @@
-use tailor_api::{Color, ColorPoint, ColorProfile, ColorTransition, FanProfilePoint};
+use tailor_api::{
+    Color, ColorInterpolation, ColorPoint, ColorProfile, ColorTransition, FanProfilePoint,
+};
 use tailor_client::TailorConnection;
 
 #[tokio::test]
@@ -187,16 +189,22 @@ async fn test_keyboard() {
             color: Color { r: 0, g: 255, b: 0 },
             transition: ColorTransition::Linear,
             transition_time: 3000,
+            hold_time: 0,
+            interpolation: ColorInterpolation::Srgb,
         },
         ColorPoint {
             color: Color { r: 255, g: 0, b: 0 },
             transition: ColorTransition::Linear,
             transition_time: 3000,
+            hold_time: 0,
+            interpolation: ColorInterpolation::Srgb,
         },
         ColorPoint {
             color: Color { r: 0, g: 0, b: 255 },
             transition: ColorTransition::Linear,
             transition_time: 3000,
+            hold_time: 0,
+            interpolation: ColorInterpolation::Srgb,
         },
     ]);
 
@@ -143,6 +143,9 @@ impl FactoryComponent for Profile {
                     function: device.function.clone(),
                     profile: "default".to_owned(),
                     mode: device.mode,
+                    brightness: None,
+                    gamma_correction: false,
+                    phase_offset: 0,
                 })
             }
         }
@@ -154,10 +157,18 @@ impl FactoryComponent for Profile {
         {
             let mut guard = leds.guard();
             for profile in &info.leds {
+                let device = capabilities.led_devices.iter().find(|device| {
+                    device.device_name == profile.device_name && device.function == profile.function
+                });
                 let device_info = LedDeviceInfo {
                     device_name: profile.device_name.clone(),
                     function: profile.function.clone(),
                     mode: profile.mode,
+                    zones: device.map_or(1, |device| device.zones),
+                    layout: device.map(|device| device.layout).unwrap_or_default(),
+                    channels: device
+                        .map(|device| device.channels.clone())
+                        .unwrap_or_default(),
                 };
                 let index = led_profiles
                     .iter()
@@ -165,6 +176,9 @@ impl FactoryComponent for Profile {
                     .unwrap_or_default();
                 guard.push_back(ProfileItemLedInit {
                     device_info,
+                    brightness: profile.brightness,
+                    gamma_correction: profile.gamma_correction,
+                    phase_offset: profile.phase_offset,
                     led_profiles: led_profiles.clone(),
                     index,
                 });
@@ -237,6 +251,7 @@ impl FactoryComponent for Profile {
                 self.info = ProfileInfo {
                     leds,
                     fans,
+                    fan_roles: self.info.fan_roles.clone(),
                     performance_profile,
                 };
 
@@ -10,11 +10,17 @@ use crate::templates;
 #[derive(Debug)]
 pub struct ProfileItemLed {
     device_info: LedDeviceInfo,
+    brightness: Option<u8>,
+    gamma_correction: bool,
+    phase_offset: u32,
     combo_box: Controller<SimpleComboBox<String>>,
 }
 
 pub struct ProfileItemLedInit {
     pub device_info: LedDeviceInfo,
+    pub brightness: Option<u8>,
+    pub gamma_correction: bool,
+    pub phase_offset: u32,
     pub led_profiles: Vec<String>,
     pub index: usize,
 }
@@ -52,6 +58,9 @@ impl FactoryComponent for ProfileItemLed {
     fn init_model(init: Self::Init, _index: &DynamicIndex, sender: FactorySender<Self>) -> Self {
         let ProfileItemLedInit {
             device_info,
+            brightness,
+            gamma_correction,
+            phase_offset,
             led_profiles,
             index,
         } = init;
@@ -63,6 +72,9 @@ impl FactoryComponent for ProfileItemLed {
             .forward(sender.output_sender(), |output| output as u8);
         Self {
             device_info,
+            brightness,
+            gamma_correction,
+            phase_offset,
             combo_box,
         }
     }
@@ -94,12 +106,16 @@ impl ProfileItemLed {
             device_name,
             function,
             mode,
+            ..
         } = self.device_info.clone();
         LedProfile {
             device_name,
             function,
             profile,
             mode,
+            brightness: self.brightness,
+            gamma_correction: self.gamma_correction,
+            phase_offset: self.phase_offset,
         }
     }
 }
@@ -8,12 +8,15 @@ use relm4::{
 };
 use relm4_components::simple_combo_box::{SimpleComboBox, SimpleComboBoxMsg};
 use relm4_icons::icon_names;
-use tailor_api::{Color, ColorPoint, ColorProfile, ColorTransition};
+use tailor_api::{
+    Color, ColorInterpolation, ColorPoint, ColorProfile, ColorTransition, LedZoneLayout,
+    ReactiveProfile, ReactiveSource,
+};
 
 use super::color_button::{ColorButton, ColorButtonInput};
 use super::factories::color::ColorRow;
 use crate::components::factories::color::ColorOutput;
-use crate::state::{tailor_connection, TailorStateMsg, STATE};
+use crate::state::{hardware_capabilities, tailor_connection, TailorStateMsg, STATE};
 use crate::templates;
 
 #[derive(Debug)]
@@ -22,6 +25,7 @@ pub enum ColorProfileType {
     None,
     Single,
     Multiple,
+    Reactive,
 }
 
 impl std::fmt::Display for ColorProfileType {
@@ -31,6 +35,7 @@ impl std::fmt::Display for ColorProfileType {
             Self::None => "None",
             Self::Single => "Single",
             Self::Multiple => "Multiple",
+            Self::Reactive => "Reactive",
         })
     }
 }
@@ -40,14 +45,35 @@ pub struct LedEdit {
     color_profile_type: ColorProfileType,
     colors: FactoryVecDeque<ColorRow>,
     color_button: Controller<ColorButton>,
+    reactive_profile: ReactiveProfile,
     type_selector: Controller<SimpleComboBox<ColorProfileType>>,
+    /// Profiles of the zones that aren't currently edited.
+    zones: Vec<ColorProfile>,
+    /// The edited zone starting at 1 or 0 to use the same profile for all zones.
+    zone: usize,
+    /// The device with the most zones, which limits the zones that can be selected.
+    zone_device: Option<(u32, LedZoneLayout)>,
     visible: bool,
 }
 
+impl LedEdit {
+    fn max_zones(&self) -> u32 {
+        self.zone_device.map_or(0, |(zones, _)| zones)
+    }
+
+    fn zone_label(&self) -> String {
+        match (self.zone, self.zone_device) {
+            (0, _) | (_, None) => "All zones".to_owned(),
+            (zone, Some((_, layout))) => layout.describe(zone as u32 - 1),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum LedEditInput {
     Load(String),
     SetType(ColorProfileType),
+    SelectZone(usize),
     Up(DynamicIndex),
     Down(DynamicIndex),
     Remove(DynamicIndex),
@@ -88,6 +114,32 @@ impl Component for LedEdit {
                             set_margin_start: 6,
                             set_valign: gtk::Align::Center,
                         },
+
+                        #[wrap(Some)]
+                        set_end_widget = &gtk::Box {
+                            set_margin_end: 6,
+                            set_spacing: 6,
+                            set_valign: gtk::Align::Center,
+
+                            gtk::Label {
+                                set_label: "Zone",
+                            },
+                            gtk::SpinButton {
+                                set_tooltip_text: Some("Select a zone to give it a separate profile or 0 to use one profile for all zones"),
+                                set_adjustment: &gtk::Adjustment::new(0.0, 0.0, model.max_zones() as f64, 1.0, 1.0, 0.0),
+                                set_sensitive: model.max_zones() > 1,
+                                #[watch]
+                                set_value: model.zone as f64,
+                                connect_value_changed[sender] => move |button| {
+                                    sender.input(LedEditInput::SelectZone(button.value() as usize));
+                                },
+                            },
+                            gtk::Label {
+                                add_css_class: "dim-label",
+                                #[watch]
+                                set_label: &model.zone_label(),
+                            },
+                        },
                     },
                 },
 
@@ -143,6 +195,13 @@ impl Component for LedEdit {
                                     }
                                 }
                             }
+                            ColorProfileType::Reactive => {
+                                gtk::Label {
+                                    set_wrap: true,
+                                    #[watch]
+                                    set_label: &model.reactive_description(),
+                                }
+                            }
                         }
                     }
                 },
@@ -198,13 +257,15 @@ impl Component for LedEdit {
                     ColorProfileType::None,
                     ColorProfileType::Single,
                     ColorProfileType::Multiple,
+                    ColorProfileType::Reactive,
                 ],
             })
             .forward(sender.input_sender(), |idx| {
                 LedEditInput::SetType(match idx {
                     0 => ColorProfileType::None,
                     1 => ColorProfileType::Single,
-                    _ => ColorProfileType::Multiple,
+                    2 => ColorProfileType::Multiple,
+                    _ => ColorProfileType::Reactive,
                 })
             });
 
@@ -213,7 +274,18 @@ impl Component for LedEdit {
             color_profile_type: ColorProfileType::Loading,
             colors,
             color_button,
+            reactive_profile: ReactiveProfile::default(),
             type_selector,
+            zones: Vec::new(),
+            zone: 0,
+            zone_device: hardware_capabilities().and_then(|capabilities| {
+                capabilities
+                    .led_devices
+                    .iter()
+                    .filter(|device| device.zones > 1)
+                    .max_by_key(|device| device.zones)
+                    .map(|device| (device.zones, device.layout))
+            }),
             visible: false,
         };
 
@@ -233,21 +305,16 @@ impl Component for LedEdit {
         self.visible = true;
 
         match color_profile {
-            ColorProfile::None => {
-                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(0));
+            ColorProfile::Zones(zones) if !zones.is_empty() => {
+                let first_zone = zones[0].clone();
+                self.zones = zones;
+                self.zone = 1;
+                self.load_profile(first_zone);
             }
-            ColorProfile::Single(color) => {
-                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(1));
-                self.color_button.emit(ColorButtonInput::UpdateColor(color));
-            }
-            ColorProfile::Multiple(color_profile) => {
-                self.color_profile_type = ColorProfileType::Multiple;
-                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(2));
-                let mut guard = self.colors.guard();
-                guard.clear();
-                for color_point in color_profile {
-                    guard.push_back(color_point);
-                }
+            color_profile => {
+                self.zones.clear();
+                self.zone = 0;
+                self.load_profile(color_profile);
             }
         }
     }
@@ -270,6 +337,29 @@ impl Component for LedEdit {
             LedEditInput::SetType(ty) => {
                 self.color_profile_type = ty;
             }
+            LedEditInput::SelectZone(zone) => {
+                if zone == self.zone {
+                    return;
+                }
+
+                let current = self.compile_editor();
+                if zone == 0 {
+                    // Use the profile of the edited zone for all zones.
+                    self.zones.clear();
+                } else {
+                    if self.zone == 0 {
+                        // Start with the same profile for every zone.
+                        self.zones = vec![current; zone];
+                    } else {
+                        self.zones[self.zone - 1] = current;
+                        if self.zones.len() < zone {
+                            self.zones.resize(zone, ColorProfile::None);
+                        }
+                    }
+                    self.load_profile(self.zones[zone - 1].clone());
+                }
+                self.zone = zone;
+            }
             LedEditInput::Apply => {
                 STATE.emit(TailorStateMsg::AddLedProfile {
                     name: self.profile_name.clone().unwrap(),
@@ -293,6 +383,8 @@ impl Component for LedEdit {
                             b: 255,
                         },
                         transition_time: 1000,
+                        hold_time: 0,
+                        interpolation: ColorInterpolation::Srgb,
                     });
                 self.colors.guard().push_back(last_elem);
             }
@@ -320,7 +412,49 @@ impl Component for LedEdit {
 }
 
 impl LedEdit {
+    /// Show a profile in the editor.
+    fn load_profile(&mut self, color_profile: ColorProfile) {
+        match color_profile {
+            ColorProfile::None => {
+                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(0));
+            }
+            ColorProfile::Single(color) => {
+                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(1));
+                self.color_button.emit(ColorButtonInput::UpdateColor(color));
+            }
+            ColorProfile::Multiple(color_profile) => {
+                self.color_profile_type = ColorProfileType::Multiple;
+                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(2));
+                let mut guard = self.colors.guard();
+                guard.clear();
+                for color_point in color_profile {
+                    guard.push_back(color_point);
+                }
+            }
+            ColorProfile::Reactive(reactive_profile) => {
+                self.reactive_profile = reactive_profile;
+                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(3));
+            }
+            // Zones can't be nested.
+            ColorProfile::Zones(_) => {
+                self.type_selector.emit(SimpleComboBoxMsg::SetActiveIdx(0));
+            }
+        }
+    }
+
+    /// The profile of the whole device.
     fn compile(&self) -> ColorProfile {
+        if self.zone == 0 {
+            self.compile_editor()
+        } else {
+            let mut zones = self.zones.clone();
+            zones[self.zone - 1] = self.compile_editor();
+            ColorProfile::Zones(zones)
+        }
+    }
+
+    /// The profile currently shown in the editor.
+    fn compile_editor(&self) -> ColorProfile {
         match self.color_profile_type {
             ColorProfileType::Loading | ColorProfileType::None => ColorProfile::None,
             ColorProfileType::Single => {
@@ -329,6 +463,24 @@ impl LedEdit {
             ColorProfileType::Multiple => {
                 ColorProfile::Multiple(self.colors.iter().map(|row| row.inner.clone()).collect())
             }
+            ColorProfileType::Reactive => ColorProfile::Reactive(self.reactive_profile.clone()),
         }
     }
+
+    fn reactive_description(&self) -> String {
+        let ReactiveProfile { source, points, .. } = &self.reactive_profile;
+        let (source, unit) = match source {
+            ReactiveSource::Temperature => ("temperature", "°C"),
+            ReactiveSource::Battery => ("battery level", "%"),
+            ReactiveSource::CpuLoad => ("CPU load", "%"),
+        };
+        let points: Vec<String> = points
+            .iter()
+            .map(|point| {
+                let Color { r, g, b } = point.color;
+                format!("{}{unit}: #{r:02x}{g:02x}{b:02x}", point.value)
+            })
+            .collect();
+        format!("The color follows the {source} ({})", points.join(", "))
+    }
 }
@@ -111,6 +111,10 @@ pub enum TailorStateMsg {
     DeleteProfile(String),
     DeleteFanProfile(String),
     DeleteLedProfile(String),
+    /// The fan profile was removed by the daemon.
+    FanProfileRemoved(String),
+    /// The LED profile was removed by the daemon.
+    LedProfileRemoved(String),
     OverwriteColor(Color),
     OverwriteFanSpeed {
         fan_idx: u8,
@@ -151,9 +155,31 @@ impl Reducible for TailorState {
                     {
                         let name = name.clone();
                         let profile = profile.clone();
+                        let exists = state.profiles.iter().any(|profile| profile.name == name);
                         let connection = state.connection.clone();
                         relm4::spawn(async move {
-                            handle_result(connection.add_global_profile(&name, &profile).await);
+                            if exists {
+                                // Only change the fields that were edited, so
+                                // the other fields are still inherited.
+                                let Some(mut definition) = handle_result(
+                                    connection.get_global_profile_definition(&name).await,
+                                ) else {
+                                    return;
+                                };
+                                let Some(previous) =
+                                    handle_result(connection.get_global_profile(&name).await)
+                                else {
+                                    return;
+                                };
+                                definition.update(&previous, profile);
+                                handle_result(
+                                    connection
+                                        .add_global_profile_definition(&name, &definition)
+                                        .await,
+                                );
+                            } else {
+                                handle_result(connection.add_global_profile(&name, &profile).await);
+                            }
                             if let Some(active_name) =
                                 handle_result(connection.get_active_global_profile_name().await)
                             {
@@ -315,31 +341,65 @@ impl Reducible for TailorState {
                 }
             }
             TailorStateMsg::DeleteFanProfile(name) => {
-                if let Some(state) = self.get_mut() {
+                if let Some(state) = self.get() {
                     let connection = state.connection.clone();
-                    let profiles = state.get_mut_fan_profiles();
-                    if let Some(pos) = profiles.iter().position(|p| p == &name) {
+                    relm4::spawn(async move {
+                        // Warn instead of breaking the global profiles that use it.
+                        let Some(dependents) =
+                            handle_result(connection.list_fan_profile_dependents(&name).await)
+                        else {
+                            return;
+                        };
+                        if !dependents.is_empty() {
+                            STATE.emit(TailorStateMsg::Error(format!(
+                                "Profile `{name}` is still used by: {}",
+                                dependents.join(", ")
+                            )));
+                        } else if handle_result(connection.remove_fan_profile(&name).await)
+                            .is_some()
                         {
-                            let name = name.clone();
-                            relm4::spawn(async move {
-                                handle_result(connection.remove_fan_profile(&name).await);
-                            });
+                            STATE.emit(TailorStateMsg::FanProfileRemoved(name));
                         }
+                    });
+                }
+                return false;
+            }
+            TailorStateMsg::FanProfileRemoved(name) => {
+                if let Some(state) = self.get_mut() {
+                    let profiles = state.get_mut_fan_profiles();
+                    if let Some(pos) = profiles.iter().position(|p| p == &name) {
                         profiles.remove(pos);
                     }
                 }
             }
             TailorStateMsg::DeleteLedProfile(name) => {
-                if let Some(state) = self.get_mut() {
+                if let Some(state) = self.get() {
                     let connection = state.connection.clone();
-                    let profiles = state.get_mut_led_profiles();
-                    if let Some(pos) = profiles.iter().position(|p| p == &name) {
+                    relm4::spawn(async move {
+                        // Warn instead of breaking the global profiles that use it.
+                        let Some(dependents) =
+                            handle_result(connection.list_led_profile_dependents(&name).await)
+                        else {
+                            return;
+                        };
+                        if !dependents.is_empty() {
+                            STATE.emit(TailorStateMsg::Error(format!(
+                                "Profile `{name}` is still used by: {}",
+                                dependents.join(", ")
+                            )));
+                        } else if handle_result(connection.remove_led_profile(&name).await)
+                            .is_some()
                         {
-                            let name = name.clone();
-                            relm4::spawn(async move {
-                                handle_result(connection.remove_led_profile(&name).await);
-                            });
+                            STATE.emit(TailorStateMsg::LedProfileRemoved(name));
                         }
+                    });
+                }
+                return false;
+            }
+            TailorStateMsg::LedProfileRemoved(name) => {
+                if let Some(state) = self.get_mut() {
+                    let profiles = state.get_mut_led_profiles();
+                    if let Some(pos) = profiles.iter().position(|p| p == &name) {
                         profiles.remove(pos);
                     }
                 }
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use zbus::{fdo, message::Header, names::BusName, proxy, zvariant::Value, Connection};
+
+/// Let polkit ask the user for a password if necessary.
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+/// The polkit actions of the daemon, see `com.tux.Tailor.policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Activate another global profile.
+    SwitchProfile,
+    /// Add, change, rename, import or remove shared profiles
+    /// and the schedules and rules.
+    EditProfiles,
+    /// Add, change, rename, import or remove profiles in the layer of the caller.
+    EditOwnProfiles,
+    /// Override fan speeds, LED colors and the performance profile.
+    OverrideHardware,
+}
+
+impl Action {
+    fn id(self) -> &'static str {
+        match self {
+            Action::SwitchProfile => "com.tux.Tailor.switch-profile",
+            Action::EditProfiles => "com.tux.Tailor.edit-profiles",
+            Action::EditOwnProfiles => "com.tux.Tailor.edit-own-profiles",
+            Action::OverrideHardware => "com.tux.Tailor.override-hardware",
+        }
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    #[allow(clippy::type_complexity)]
+    async fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: &HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// The UID of the sender of a D-Bus message.
+pub async fn caller_uid(connection: &Connection, header: &Header<'_>) -> fdo::Result<u32> {
+    let sender = header
+        .sender()
+        .ok_or_else(|| fdo::Error::AccessDenied("Unknown sender".to_owned()))?;
+    fdo::DBusProxy::new(connection)
+        .await?
+        .get_connection_unix_user(BusName::Unique(sender.to_owned()))
+        .await
+}
+
+/// Check with polkit whether the sender of a D-Bus message may perform the action.
+/// Root is always authorized, so the daemon stays usable without polkit.
+pub async fn authorize(
+    connection: &Connection,
+    header: &Header<'_>,
+    action: Action,
+) -> fdo::Result<()> {
+    if caller_uid(connection, header).await? == 0 {
+        return Ok(());
+    }
+
+    let sender = header
+        .sender()
+        .ok_or_else(|| fdo::Error::AccessDenied("Unknown sender".to_owned()))?;
+    let subject = (
+        "system-bus-name",
+        HashMap::from([("name", Value::from(sender.as_str()))]),
+    );
+
+    let authority = AuthorityProxy::new(connection).await?;
+    let (authorized, _, _) = authority
+        .check_authorization(
+            &subject,
+            action.id(),
+            &HashMap::new(),
+            ALLOW_USER_INTERACTION,
+            "",
+        )
+        .await
+        .map_err(|err| {
+            tracing::warn!("Polkit authorization failed: `{err}`");
+            fdo::Error::AccessDenied(format!("Couldn't check authorization: {err}"))
+        })?;
+
+    if authorized {
+        Ok(())
+    } else {
+        Err(fdo::Error::AccessDenied(format!(
+            "Not authorized to perform `{}`",
+            action.id()
+        )))
+    }
+}
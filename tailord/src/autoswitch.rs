@@ -0,0 +1,259 @@
+use std::{sync::Arc, time::Duration};
+
+use tailor_api::RevertCondition;
+use tokio::sync::{broadcast::error::RecvError, Notify};
+use zbus::{fdo, object_server::SignalEmitter};
+
+use crate::{
+    dbus::ProfileInterface,
+    layers::Layers,
+    profiles::PROFILE_DIR,
+    rules::{self, current_rule},
+    schedule::{self, current_schedule, local_time},
+    state::{unix_time, AutomaticState, State, Trigger},
+    suspend::get_suspend_receiver,
+};
+
+/// How often the running processes are checked if there are process rules.
+const PROCESS_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the AC adapter is checked while a profile is active until it changes.
+const AC_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+#[derive(Clone, Default)]
+pub struct AutoSwitchHandle {
+    changed: Arc<Notify>,
+}
+
+impl AutoSwitchHandle {
+    /// Evaluate the schedules and rules again after they or the lock were changed.
+    pub fn notify_changed(&self) {
+        self.changed.notify_one();
+    }
+}
+
+/// Activates profiles for schedules and process rules.
+///
+/// Process rules take precedence over schedules. When a schedule or
+/// rule starts, its profile is activated. When it ends, the previous
+/// profile is activated again, unless another profile was activated
+/// manually in the meantime. Manually activated profiles lock the
+/// automatic switching until the lock is cleared.
+///
+/// Temporary profiles pause schedules and rules as well and
+/// are reverted once their [`RevertCondition`] is met.
+pub struct AutoSwitchRuntime {
+    handle: AutoSwitchHandle,
+    profiles: ProfileInterface,
+    /// The deadline and the remaining minutes of the last countdown signal.
+    countdown: Option<(u64, u64)>,
+}
+
+impl AutoSwitchRuntime {
+    pub fn new(handle: AutoSwitchHandle, profiles: ProfileInterface) -> Self {
+        Self {
+            handle,
+            profiles,
+            countdown: None,
+        }
+    }
+
+    pub async fn run(mut self, emitter: SignalEmitter<'static>) {
+        let mut suspend_receiver = get_suspend_receiver();
+        let mut suspend_closed = false;
+        let mut suspended = false;
+        let mut resumed = false;
+        loop {
+            let (_, _, remaining) = local_time();
+            let mut delay = Duration::from_secs(remaining);
+
+            // Wait until the device woke up again, so resuming can be detected.
+            if !suspended {
+                match self.update_temporary(&emitter, resumed).await {
+                    Ok(Some(max_delay)) => delay = delay.min(max_delay),
+                    Ok(None) => match self.evaluate().await {
+                        Ok(true) => delay = delay.min(PROCESS_SCAN_INTERVAL),
+                        Ok(false) => {}
+                        Err(err) => {
+                            tracing::warn!("Failed to switch the profile automatically: `{err}`")
+                        }
+                    },
+                    Err(err) => tracing::warn!("Failed to revert the temporary profile: `{err}`"),
+                }
+                resumed = false;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.handle.changed.notified() => {}
+                // The clock jumps during suspend, so evaluate again after waking up.
+                message = suspend_receiver.recv(), if !suspend_closed => match message {
+                    Ok(true) => suspended = true,
+                    Ok(false) => {
+                        resumed = suspended;
+                        suspended = false;
+                    }
+                    // Only the latest message is kept, so a suspend was missed.
+                    Err(RecvError::Lagged(_)) => suspended = true,
+                    Err(RecvError::Closed) => suspend_closed = true,
+                },
+            }
+        }
+    }
+
+    /// Revert the temporary profile if its condition is met and emit the countdown.
+    /// Returns the maximum delay until the next check while a temporary profile is active.
+    async fn update_temporary(
+        &mut self,
+        emitter: &SignalEmitter<'static>,
+        resumed: bool,
+    ) -> fdo::Result<Option<Duration>> {
+        let Some(temporary) = State::load().temporary else {
+            self.countdown = None;
+            return Ok(None);
+        };
+
+        let now = unix_time();
+        let ended = match temporary.until {
+            RevertCondition::After(_) => {
+                temporary.deadline.map_or(true, |deadline| deadline <= now)
+            }
+            RevertCondition::Resume => resumed,
+            RevertCondition::AcChange => ac_online() != temporary.ac_online,
+        };
+
+        if ended {
+            self.countdown = None;
+            // Another profile might have been activated in the meantime.
+            let ended = State::update(|state| {
+                if state.temporary.as_ref() == Some(&temporary) {
+                    state.end_temporary();
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            })
+            .await?;
+            if !ended {
+                return Ok(None);
+            }
+            self.profiles.apply_active_profile().await?;
+            ProfileInterface::temporary_profile_ended(emitter, &temporary.profile).await?;
+            return Ok(None);
+        }
+
+        match temporary.deadline {
+            Some(deadline) => {
+                let remaining = deadline - now;
+                let minutes = (remaining + 59) / 60;
+                if self.countdown != Some((deadline, minutes)) {
+                    self.countdown = Some((deadline, minutes));
+                    ProfileInterface::temporary_profile_countdown(
+                        emitter,
+                        &temporary.profile,
+                        remaining,
+                    )
+                    .await?;
+                }
+                // Wake up when the next minute of the countdown starts.
+                Ok(Some(Duration::from_secs(remaining - (minutes - 1) * 60)))
+            }
+            None if temporary.until == RevertCondition::AcChange => Ok(Some(AC_POLL_INTERVAL)),
+            None => Ok(Some(Duration::MAX)),
+        }
+    }
+
+    /// Activate the profile of the current schedule or rule.
+    /// Returns whether there are process rules that need to be checked regularly.
+    async fn evaluate(&mut self) -> fdo::Result<bool> {
+        let rules = rules::load_rules().await;
+        let has_rules = !rules.is_empty();
+        let state = State::load();
+        if state.locked || state.temporary.is_some() {
+            return Ok(has_rules);
+        }
+
+        let current = if rules.is_empty() {
+            None
+        } else {
+            current_rule(&rules, &rules::running_processes())
+                .map(|(name, rule)| (Trigger::Rule(name.clone()), rule.profile.clone()))
+        };
+        let current = match current {
+            Some(current) => Some(current),
+            None => {
+                let schedules = schedule::load_schedules().await;
+                let (day, time, _) = local_time();
+                current_schedule(&schedules, day, time).map(|(name, schedule)| {
+                    (Trigger::Schedule(name.clone()), schedule.profile.clone())
+                })
+            }
+        };
+
+        let changed = State::update(|state| {
+            // The state might have changed while the rules were checked.
+            if state.locked || state.temporary.is_some() {
+                return Ok(false);
+            }
+
+            match (current, state.automatic.clone()) {
+                (Some((trigger, _)), Some(active)) if active.trigger == trigger => {
+                    return Ok(false)
+                }
+                (None, None) => return Ok(false),
+                (Some((trigger, profile)), active) => {
+                    Layers::SYSTEM.resolve(&PROFILE_DIR, &profile)?;
+                    tracing::info!("{trigger} activates profile `{profile}`");
+                    // Keep the profile from before the first trigger of a sequence.
+                    let (previous_profile, previous_user) = match active {
+                        Some(active) => (active.previous_profile, active.previous_user),
+                        None => (state.active_profile.clone(), state.active_user),
+                    };
+                    state.automatic = Some(AutomaticState {
+                        trigger,
+                        profile: profile.clone(),
+                        previous_profile,
+                        previous_user,
+                    });
+                    state.active_profile = Some(profile);
+                    state.active_user = None;
+                }
+                (None, Some(active)) => {
+                    tracing::info!("{} ended", active.trigger);
+                    state.restore_previous(
+                        &active.profile,
+                        active.previous_profile,
+                        active.previous_user,
+                    );
+                    state.automatic = None;
+                }
+            }
+            Ok(true)
+        })
+        .await?;
+
+        if changed {
+            self.profiles.apply_active_profile().await?;
+        }
+        Ok(has_rules)
+    }
+}
+
+/// Whether the first AC adapter is plugged in,
+/// or [`None`] if the device has no AC adapter.
+pub fn ac_online() -> Option<bool> {
+    for entry in std::fs::read_dir(POWER_SUPPLY_PATH).ok()?.flatten() {
+        let path = entry.path();
+        let is_mains = std::fs::read_to_string(path.join("type"))
+            .map(|ty| ty.trim() == "Mains")
+            .unwrap_or(false);
+
+        if is_mains {
+            let online = std::fs::read_to_string(path.join("online")).ok()?;
+            return Some(online.trim() == "1");
+        }
+    }
+    None
+}
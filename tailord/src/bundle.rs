@@ -0,0 +1,170 @@
+use std::collections::{btree_map::Entry, BTreeMap, HashMap};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tailor_api::{ConflictPolicy, ProfileBundle, SCHEMA_VERSION};
+use zbus::fdo;
+
+use crate::{
+    layers::Layers,
+    profiles::{FAN_DIR, KEYBOARD_DIR, PROFILE_DIR},
+    store::{self, Transaction},
+};
+
+/// Collect a global profile and all fan and LED profiles it references.
+/// Inherited fields are included, so the bundle doesn't depend on base profiles.
+pub async fn export(layers: &Layers, name: &str) -> fdo::Result<ProfileBundle> {
+    let profile = store::read_resolved_profile(layers, name)?;
+    let mut bundle = ProfileBundle::new(name.to_owned(), profile.clone());
+
+    for fan in profile.fan_profiles().cloned() {
+        if let Entry::Vacant(entry) = bundle.fan_profiles.entry(fan) {
+            let fan_profile = store::read_profile_json(layers, &FAN_DIR, entry.key()).await?;
+            entry.insert(fan_profile);
+        }
+    }
+    for led in profile.leds {
+        if let Entry::Vacant(entry) = bundle.led_profiles.entry(led.profile) {
+            let led_profile = store::read_profile_json(layers, &KEYBOARD_DIR, entry.key()).await?;
+            entry.insert(led_profile);
+        }
+    }
+    Ok(bundle)
+}
+
+/// Store all profiles of a bundle in the writable layer in a single transaction.
+/// Returns the name of the imported global profile,
+/// which differs from the bundle if it was renamed.
+pub async fn import(
+    layers: &Layers,
+    bundle: ProfileBundle,
+    policy: ConflictPolicy,
+) -> fdo::Result<String> {
+    let ProfileBundle {
+        version,
+        name,
+        mut profile,
+        fan_profiles,
+        led_profiles,
+    } = bundle;
+    if version > SCHEMA_VERSION {
+        return Err(fdo::Error::InvalidArgs(format!(
+            "Unsupported schema version {version}, the latest supported version is {SCHEMA_VERSION}"
+        )));
+    }
+
+    let mut transaction = Transaction::default();
+
+    let fan_names =
+        import_profiles(layers, &mut transaction, &FAN_DIR, fan_profiles, policy).await?;
+    let fan_profiles = layers.names(&FAN_DIR).await?;
+    for fan in profile.fan_profiles_mut() {
+        if let Some(new_name) = fan_names.get(fan) {
+            new_name.clone_into(fan);
+        } else if !fan_profiles.contains(fan) {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "The bundle doesn't contain the fan profile `{fan}`"
+            )));
+        }
+    }
+
+    let led_names = import_profiles(
+        layers,
+        &mut transaction,
+        &KEYBOARD_DIR,
+        led_profiles,
+        policy,
+    )
+    .await?;
+    let led_profiles = layers.names(&KEYBOARD_DIR).await?;
+    for led in &mut profile.leds {
+        if let Some(new_name) = led_names.get(&led.profile) {
+            new_name.clone_into(&mut led.profile);
+        } else if !led_profiles.contains(&led.profile) {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "The bundle doesn't contain the LED profile `{}`",
+                led.profile
+            )));
+        }
+    }
+
+    let profile_names = import_profiles(
+        layers,
+        &mut transaction,
+        &PROFILE_DIR,
+        BTreeMap::from([(name.clone(), profile)]),
+        policy,
+    )
+    .await?;
+
+    transaction.commit().await?;
+    Ok(profile_names.get(&name).cloned().unwrap_or(name))
+}
+
+/// Stage the profiles in the transaction and resolve conflicts with existing profiles.
+/// Returns the stored name of each profile.
+async fn import_profiles<T>(
+    layers: &Layers,
+    transaction: &mut Transaction,
+    base_path: &str,
+    profiles: BTreeMap<String, T>,
+    policy: ConflictPolicy,
+) -> fdo::Result<HashMap<String, String>>
+where
+    T: Serialize + DeserializeOwned + PartialEq,
+{
+    let mut taken = layers.names(base_path).await?;
+    let dir = layers.writable_dir(base_path)?;
+    let mut names = HashMap::new();
+
+    for (name, profile) in profiles {
+        let target = if !taken.contains(&name) {
+            Some(name.clone())
+        } else if store::read_profile_json::<T>(layers, base_path, &name)
+            .await
+            .is_ok_and(|existing| existing == profile)
+        {
+            // Already imported before.
+            None
+        } else {
+            match policy {
+                ConflictPolicy::Fail => {
+                    return Err(fdo::Error::FileExists(format!(
+                        "Profile `{name}` already exists with a different content"
+                    )))
+                }
+                ConflictPolicy::Overwrite => Some(name.clone()),
+                ConflictPolicy::Skip => None,
+                ConflictPolicy::Rename => Some(unique_name(&name, &taken)),
+            }
+        };
+
+        if let Some(target) = target {
+            transaction.write_json(&dir, &target, &profile).await?;
+            taken.push(target.clone());
+            names.insert(name, target);
+        } else {
+            names.insert(name.clone(), name);
+        }
+    }
+    Ok(names)
+}
+
+/// The first name of the form `name_N` that isn't taken yet.
+fn unique_name(name: &str, taken: &[String]) -> String {
+    (1..)
+        .map(|idx| format!("{name}_{idx}"))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::unique_name;
+
+    #[test]
+    fn unique_names() {
+        let taken = ["silent".to_owned(), "silent_1".to_owned()];
+        assert_eq!(unique_name("silent", &taken), "silent_2");
+        assert_eq!(unique_name("loud", &taken), "loud_1");
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use once_cell::sync::{Lazy, OnceCell};
+use toml_edit::{DocumentMut, Item, Value};
+
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/tailord/tailord.toml";
+
+/// The settings that are currently used.
+static CONFIG: Lazy<RwLock<Config>> = Lazy::new(|| RwLock::new(Config::default()));
+
+/// Where the settings were loaded from, used to reload them.
+static SOURCE: OnceCell<(PathBuf, Vec<(String, String)>)> = OnceCell::new();
+
+/// Settings of the daemon, see `tailord.toml` for the documentation of the keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub paths: PathConfig,
+    pub dbus: DbusConfig,
+    pub led: LedConfig,
+    pub fan: FanConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathConfig {
+    /// The directory of the system profiles and the state of the daemon.
+    pub root: String,
+    /// The directory of the read-only vendor profiles.
+    pub vendor: String,
+    /// The directory of the state that changes at runtime, like the active profile.
+    pub state: String,
+}
+
+impl PathConfig {
+    /// A path inside of the root directory.
+    pub fn system_path(&self, path: &str) -> String {
+        format!("{}/{path}", self.root.trim_end_matches('/'))
+    }
+
+    /// A path inside of the vendor directory.
+    pub fn vendor_path(&self, path: &str) -> String {
+        format!("{}/{path}", self.vendor.trim_end_matches('/'))
+    }
+
+    /// A path inside of the state directory.
+    pub fn state_path(&self, path: &str) -> String {
+        format!("{}/{path}", self.state.trim_end_matches('/'))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbusConfig {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedConfig {
+    /// The shortest time between two frames of a color transition.
+    pub min_frame_interval_ms: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FanConfig {
+    /// The delay between two fan speed updates if the temperature is stable.
+    pub max_delay_ms: u32,
+    /// How fast the delay decreases if the temperature changes.
+    pub delay_decay: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            paths: PathConfig {
+                root: "/etc/tailord".to_owned(),
+                vendor: "/usr/share/tailord".to_owned(),
+                state: "/var/lib/tailord".to_owned(),
+            },
+            dbus: DbusConfig {
+                name: "com.tux.Tailor".to_owned(),
+            },
+            led: LedConfig {
+                min_frame_interval_ms: 80,
+            },
+            fan: FanConfig {
+                max_delay_ms: 2000,
+                delay_decay: 7.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml_edit::TomlError),
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "Failed to read the configuration: {err}"),
+            ConfigError::Parse(err) => write!(f, "Invalid configuration: {err}"),
+            ConfigError::Invalid(msg) => write!(f, "Invalid configuration: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Parse the settings and apply the overrides of the command line.
+    /// Keys that are missing use their default value.
+    pub fn parse(data: &str, overrides: &[(String, String)]) -> Result<Self, ConfigError> {
+        let mut doc: DocumentMut = data.parse().map_err(ConfigError::Parse)?;
+        for (key, value) in overrides {
+            let Some((table, name)) = key.split_once('.') else {
+                return Err(ConfigError::Invalid(format!("Unknown key `{key}`")));
+            };
+            // Values that aren't valid TOML are strings, so paths don't need quotes.
+            let value = value
+                .parse::<Value>()
+                .unwrap_or_else(|_| Value::from(value.as_str()));
+            doc[table][name] = Item::Value(value);
+        }
+
+        let mut config = Self::default();
+        for (table_name, table) in doc.iter() {
+            let table = table
+                .as_table_like()
+                .ok_or_else(|| ConfigError::Invalid(format!("`{table_name}` isn't a table")))?;
+            for (name, item) in table.iter() {
+                let key = format!("{table_name}.{name}");
+                match key.as_str() {
+                    "paths.root" => config.paths.root = path_value(&key, item)?,
+                    "paths.vendor" => config.paths.vendor = path_value(&key, item)?,
+                    "paths.state" => config.paths.state = path_value(&key, item)?,
+                    "dbus.name" => {
+                        let name = string_value(&key, item)?;
+                        zbus::names::WellKnownName::try_from(name.as_str())
+                            .map_err(|err| ConfigError::Invalid(format!("`{key}`: {err}")))?;
+                        config.dbus.name = name;
+                    }
+                    "led.min_frame_interval_ms" => {
+                        config.led.min_frame_interval_ms = int_value(&key, item, 10..=1000)?
+                    }
+                    "fan.max_delay_ms" => {
+                        config.fan.max_delay_ms = int_value(&key, item, 100..=60_000)?
+                    }
+                    "fan.delay_decay" => {
+                        let decay = item
+                            .as_float()
+                            .or_else(|| item.as_integer().map(|value| value as f64))
+                            .ok_or_else(|| invalid_type(&key, "a number"))?;
+                        if !(decay > 0.0 && decay.is_finite()) {
+                            return Err(ConfigError::Invalid(format!(
+                                "`{key}` must be a positive number"
+                            )));
+                        }
+                        config.fan.delay_decay = decay;
+                    }
+                    _ => return Err(ConfigError::Invalid(format!("Unknown key `{key}`"))),
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Load the settings from a file. A missing file uses the default settings.
+    pub fn load(path: &Path, overrides: &[(String, String)]) -> Result<Self, ConfigError> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(ConfigError::Io(err)),
+        };
+        Self::parse(&data, overrides)
+    }
+}
+
+fn invalid_type(key: &str, expected: &str) -> ConfigError {
+    ConfigError::Invalid(format!("`{key}` must be {expected}"))
+}
+
+fn string_value(key: &str, item: &Item) -> Result<String, ConfigError> {
+    item.as_str()
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| invalid_type(key, "a string"))
+}
+
+fn path_value(key: &str, item: &Item) -> Result<String, ConfigError> {
+    let path = string_value(key, item)?;
+    if Path::new(&path).is_absolute() {
+        Ok(path)
+    } else {
+        Err(invalid_type(key, "an absolute path"))
+    }
+}
+
+fn int_value<T: TryFrom<i64>>(
+    key: &str,
+    item: &Item,
+    range: std::ops::RangeInclusive<i64>,
+) -> Result<T, ConfigError> {
+    item.as_integer()
+        .filter(|value| range.contains(value))
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "`{key}` must be an integer from {} to {}",
+                range.start(),
+                range.end()
+            ))
+        })
+}
+
+/// Parse a `KEY=VALUE` override of the command line.
+pub fn parse_override(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .ok_or_else(|| format!("Expected KEY=VALUE: `{arg}`"))
+}
+
+/// Load the settings at startup.
+pub fn init(path: PathBuf, overrides: Vec<(String, String)>) -> Result<(), ConfigError> {
+    let config = Config::load(&path, &overrides)?;
+    tracing::info!("Loaded configuration from `{}`", path.display());
+    *CONFIG.write().unwrap() = config;
+    SOURCE.set((path, overrides)).ok();
+    Ok(())
+}
+
+/// Load the settings again. The paths and the D-Bus name
+/// are only read at startup and keep their values.
+pub fn reload() -> Result<(), ConfigError> {
+    let Some((path, overrides)) = SOURCE.get() else {
+        return Ok(());
+    };
+    let config = Config::load(path, overrides)?;
+
+    let mut current = CONFIG.write().unwrap();
+    if config.paths != current.paths || config.dbus != current.dbus {
+        tracing::warn!("Changes of `paths` and `dbus` require a restart of tailord");
+    }
+    current.led = config.led;
+    current.fan = config.fan;
+    tracing::info!("Reloaded configuration from `{}`", path.display());
+    Ok(())
+}
+
+pub fn get() -> Config {
+    CONFIG.read().unwrap().clone()
+}
+
+pub fn led() -> LedConfig {
+    CONFIG.read().unwrap().led
+}
+
+pub fn fan() -> FanConfig {
+    CONFIG.read().unwrap().fan
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+
+    #[test]
+    fn parse_config() {
+        assert_eq!(Config::parse("", &[]).unwrap(), Config::default());
+
+        let data = "[led]\nmin_frame_interval_ms = 40\n\n[fan]\ndelay_decay = 5\n";
+        let overrides = [("paths.root".to_owned(), "/tmp/tailord".to_owned())];
+        let config = Config::parse(data, &overrides).unwrap();
+        assert_eq!(config.led.min_frame_interval_ms, 40);
+        assert_eq!(config.fan.delay_decay, 5.0);
+        assert_eq!(config.paths.system_path("fan/"), "/tmp/tailord/fan/");
+
+        assert!(Config::parse("[led]\nmin_frame_interval_ms = 0\n", &[]).is_err());
+        assert!(Config::parse("[led]\nframe_rate = 10\n", &[]).is_err());
+        assert!(Config::parse("[paths]\nroot = \"tailord\"\n", &[]).is_err());
+        assert!(Config::parse("", &[("dbus.name".to_owned(), "tailor".to_owned())]).is_err());
+    }
+}
@@ -1,69 +1,198 @@
-use tailor_api::{FanProfilePoint, ProfileInfo};
-use zbus::{fdo, interface};
+use tailor_api::{FanRole, ProfileDefinition, RemovalStrategy};
+use zbus::{fdo, interface, message::Header, object_server::SignalEmitter, Connection};
 
 use crate::{
+    auth::{self, Action},
     fancontrol::FanRuntimeHandle,
+    layers::Layers,
     profiles::{Profile, FAN_DIR, PROFILE_DIR},
-    util,
+    store::{self, StoreError, Transaction},
 };
 
 pub struct FanInterface {
     pub handles: Vec<FanRuntimeHandle>,
 }
 
+impl FanInterface {
+    /// Send the fan profiles of the active global profile to the runtimes.
+    async fn reload(&self) -> fdo::Result<()> {
+        let info = Profile::load();
+        for (idx, handle) in self.handles.iter().enumerate() {
+            handle
+                .profile_sender
+                .send(info.fan_profile(idx, handle.role))
+                .await
+                .map_err(|err| fdo::Error::Failed(format!("Internal error: `{err}`")))?;
+        }
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        layers: &Layers,
+        name: &str,
+        strategy: &RemovalStrategy,
+    ) -> Result<(), StoreError> {
+        let active_changed = store::remove_referenced(
+            layers,
+            &FAN_DIR,
+            name,
+            strategy,
+            |info| info.fan_profiles().any(|fan| fan == name),
+            |info, replacement| {
+                for fan in info.fan_profiles_mut() {
+                    if fan == name {
+                        replacement.clone_into(fan);
+                    }
+                }
+            },
+        )
+        .await?;
+
+        if active_changed {
+            self.reload().await?;
+        }
+        Ok(())
+    }
+}
+
 #[interface(name = "com.tux.Tailor.Fan")]
 impl FanInterface {
-    async fn add_profile(&self, name: &str, value: &str) -> fdo::Result<()> {
-        // Verify correctness of the file.
-        serde_json::from_str::<Vec<FanProfilePoint>>(value)
-            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
-        util::write_file(FAN_DIR, name, value.as_bytes()).await?;
+    /// Emitted when a fan profile was changed on disk.
+    #[zbus(signal)]
+    pub async fn profile_changed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+
+    async fn add_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        value: &str,
+    ) -> fdo::Result<()> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        // Verifies correctness of the file.
+        store::write_profile(&layers, &FAN_DIR, name, value).await?;
 
         // Reload if the fan profile is part of the active global profile
         let info = Profile::get_active_profile_info()?;
-        if info.fans.iter().any(|info| info == name) {
-            let info = Profile::load();
-            for (idx, handle) in self.handles.iter().enumerate() {
-                handle
-                    .profile_sender
-                    .send(info.fans.get(idx).cloned().unwrap_or_default())
-                    .await
-                    .unwrap();
-            }
+        if info.fan_profiles().any(|fan| fan == name) {
+            self.reload().await?;
         }
         Ok(())
     }
 
-    async fn get_profile(&self, name: &str) -> fdo::Result<String> {
-        util::read_file(FAN_DIR, name).await
+    async fn get_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        store::read_profile(&layers, &FAN_DIR, name).await
     }
 
-    async fn list_profiles(&self) -> fdo::Result<Vec<String>> {
-        util::get_profiles(FAN_DIR).await
+    /// The names of the visible profiles.
+    async fn list_profiles(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<Vec<String>> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        layers.names(&FAN_DIR).await
     }
 
-    async fn remove_profile(&self, name: &str) -> fdo::Result<()> {
-        util::remove_file(FAN_DIR, name).await
+    /// The visible profiles as JSON list of [`ProfileEntry`](tailor_api::ProfileEntry).
+    async fn list_profile_entries(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        let entries = layers.list(&FAN_DIR).await?;
+        Ok(serde_json::to_string(&entries).unwrap())
     }
 
-    async fn rename_profile(&self, from: &str, to: &str) -> fdo::Result<Vec<String>> {
-        if self.list_profiles().await?.contains(&to.to_string()) {
-            Err(fdo::Error::InvalidArgs(format!(
-                "File `{to}` already exists"
-            )))
+    /// Fails with `com.tux.Tailor.Error.ProfileInUse` if a global profile uses the profile.
+    async fn remove_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> Result<(), StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        self.remove(&layers, name, &RemovalStrategy::Refuse).await
+    }
+
+    /// Remove the profile and update the global profiles that use it
+    /// according to the [`RemovalStrategy`] (JSON).
+    async fn remove_profile_with(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        strategy: &str,
+    ) -> Result<(), StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        let strategy: RemovalStrategy = serde_json::from_str(strategy)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        self.remove(&layers, name, &strategy).await
+    }
+
+    /// The global profiles that use the profile.
+    async fn list_dependents(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<Vec<String>> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        store::dependents(&layers, |info| info.fan_profiles().any(|fan| fan == name)).await
+    }
+
+    async fn rename_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<String>, StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        if layers.names(&FAN_DIR).await?.contains(&to.to_string()) {
+            Err(fdo::Error::InvalidArgs(format!("File `{to}` already exists")).into())
         } else {
-            let profiles = util::get_profiles(PROFILE_DIR).await?;
+            layers.ensure_writable(&FAN_DIR, from)?;
+            store::ensure_no_user_dependents(&layers, &FAN_DIR, from, |info| {
+                info.fan_profiles().any(|fan| fan == from)
+            })?;
+            let profile_dir = layers.writable_dir(&PROFILE_DIR)?;
+
+            // Update the references and the file together.
+            // Global profiles of lower layers can't be changed.
+            let mut transaction = Transaction::default();
+            let profiles = layers.list(&PROFILE_DIR).await?;
 
             for profile in profiles {
-                let mut data =
-                    if let Ok(data) = util::read_json::<ProfileInfo>(PROFILE_DIR, &profile).await {
-                        data
-                    } else {
-                        continue;
-                    };
+                if profile.origin != layers.writable() {
+                    continue;
+                }
+                let mut data = if let Ok(data) = store::read_profile_json::<ProfileDefinition>(
+                    &layers,
+                    &PROFILE_DIR,
+                    &profile.name,
+                )
+                .await
+                {
+                    data
+                } else {
+                    continue;
+                };
                 let mut changed = false;
 
-                for fan in &mut data.fans {
+                for fan in data.fan_profiles_mut() {
                     if fan == from {
                         to.clone_into(fan);
                         changed = true;
@@ -71,17 +200,27 @@ impl FanInterface {
                 }
 
                 if changed {
-                    util::write_json(PROFILE_DIR, &profile, &data).await?;
+                    transaction
+                        .write_json(&profile_dir, &profile.name, &data)
+                        .await?;
                 }
             }
 
-            util::move_file(FAN_DIR, from, to).await?;
+            transaction.rename(&layers.writable_dir(&FAN_DIR)?, from, to)?;
+            transaction.commit().await?;
 
-            self.list_profiles().await
+            Ok(layers.names(&FAN_DIR).await?)
         }
     }
 
-    async fn override_speed(&mut self, fan_idx: u8, speed: u8) -> fdo::Result<()> {
+    async fn override_speed(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        fan_idx: u8,
+        speed: u8,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::OverrideHardware).await?;
         if let Some(handle) = self.handles.get(fan_idx as usize) {
             handle
                 .fan_speed_sender
@@ -94,4 +233,27 @@ impl FanInterface {
             ))
         }
     }
+
+    /// Like `override_speed` for the fan with the given role, like `cpu` or `gpu`.
+    async fn override_speed_by_role(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        role: &str,
+        speed: u8,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::OverrideHardware).await?;
+        let role: FanRole = role.parse().map_err(fdo::Error::InvalidArgs)?;
+        if let Some(handle) = self.handles.iter().find(|handle| handle.role == Some(role)) {
+            handle
+                .fan_speed_sender
+                .send(speed)
+                .await
+                .map_err(|err| fdo::Error::Failed(format!("Internal error: `{err}`")))
+        } else {
+            Err(fdo::Error::InvalidArgs(format!(
+                "No fan found with the role `{role}`"
+            )))
+        }
+    }
 }
@@ -1,77 +1,279 @@
-use tailor_api::{Color, ColorProfile, ProfileInfo};
-use zbus::{fdo, interface};
+use std::future::pending;
+
+use futures::future::select_all;
+use tailor_api::{Color, FlashPattern, ProfileDefinition, RemovalStrategy};
+use zbus::{fdo, interface, message::Header, object_server::SignalEmitter, Connection};
 
 use crate::{
-    led::LedRuntimeHandle,
+    auth::{self, Action},
+    layers::Layers,
+    led::{runtime, LedDeviceChange, LedHandles, LedHotplug},
     profiles::{Profile, KEYBOARD_DIR, PROFILE_DIR},
-    util,
+    store::{self, StoreError, Transaction},
 };
 
+/// Flash patterns are meant to be short notifications.
+const MAX_FLASH_DURATION_MS: u64 = 30_000;
+const MAX_FLASH_REPETITIONS: u32 = 100;
+/// Every step is a write to sysfs, so limit them as well.
+const MAX_FLASH_STEPS: u64 = 2_000;
+
 pub struct LedInterface {
-    pub handles: Vec<LedRuntimeHandle>,
+    pub handles: LedHandles,
+}
+
+impl LedInterface {
+    /// Emit the `BrightnessChanged` signal whenever the brightness of
+    /// an LED device changes, either through D-Bus or the firmware.
+    pub async fn emit_brightness_changes(emitter: SignalEmitter<'static>, handles: LedHandles) {
+        let mut handles_receiver = handles.subscribe();
+
+        loop {
+            let mut receivers: Vec<_> = handles_receiver
+                .borrow_and_update()
+                .iter()
+                .map(|handle| handle.brightness_state.clone())
+                .collect();
+
+            let any_change = async {
+                if receivers.is_empty() {
+                    return pending().await;
+                }
+                let changes = receivers
+                    .iter_mut()
+                    .map(|receiver| Box::pin(receiver.changed()));
+                select_all(changes).await.0
+            };
+
+            tokio::select! {
+                res = handles_receiver.changed() => {
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                res = any_change => {
+                    if res.is_err() {
+                        // The runtime stopped, wait until its device is removed.
+                        if handles_receiver.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let brightness = handles_receiver
+                .borrow()
+                .iter()
+                .map(|handle| *handle.brightness_state.borrow())
+                .max()
+                .unwrap_or_default();
+            if let Err(err) = Self::brightness_changed(&emitter, brightness).await {
+                tracing::warn!("Failed to emit brightness change: `{err}`");
+            }
+        }
+    }
+
+    /// Send the LED settings of the active global profile to the runtimes.
+    async fn reload(&self) -> fdo::Result<()> {
+        let profile = Profile::load();
+        let handles = self.handles.get();
+        let settings = profile.led_settings(&self.handles.infos());
+        for (handle, settings) in handles.iter().zip(settings) {
+            handle
+                .settings_sender
+                .send(settings)
+                .await
+                .map_err(|err| fdo::Error::Failed(format!("Internal error: `{err}`")))?;
+        }
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        layers: &Layers,
+        name: &str,
+        strategy: &RemovalStrategy,
+    ) -> Result<(), StoreError> {
+        let active_changed = store::remove_referenced(
+            layers,
+            &KEYBOARD_DIR,
+            name,
+            strategy,
+            |info| info.leds.iter().flatten().any(|led| led.profile == name),
+            |info, replacement| {
+                for led in info.leds.iter_mut().flatten() {
+                    if led.profile == name {
+                        replacement.clone_into(&mut led.profile);
+                    }
+                }
+            },
+        )
+        .await?;
+
+        if active_changed {
+            self.reload().await?;
+        }
+        Ok(())
+    }
+
+    /// Emit the `DeviceAdded` and `DeviceRemoved` signals
+    /// whenever an LED device is plugged in or removed.
+    pub async fn emit_device_changes(emitter: SignalEmitter<'static>, mut hotplug: LedHotplug) {
+        while let Some(changes) = hotplug.next_changes().await {
+            for change in changes {
+                let res = match change {
+                    LedDeviceChange::Added(info) => {
+                        Self::device_added(&emitter, &serde_json::to_string(&info).unwrap()).await
+                    }
+                    LedDeviceChange::Removed(info) => {
+                        Self::device_removed(&emitter, &serde_json::to_string(&info).unwrap()).await
+                    }
+                };
+                if let Err(err) = res {
+                    tracing::warn!("Failed to emit LED device change: `{err}`");
+                }
+            }
+        }
+        tracing::warn!("Stop watching for LED devices");
+    }
 }
 
 #[interface(name = "com.tux.Tailor.Led")]
 impl LedInterface {
-    async fn add_profile(&self, name: &str, value: &str) -> fdo::Result<()> {
-        // Verify correctness of the file.
-        serde_json::from_str::<ColorProfile>(value)
-            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
-        util::write_file(KEYBOARD_DIR, name, value.as_bytes()).await?;
+    async fn add_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        value: &str,
+    ) -> fdo::Result<()> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        // Verifies correctness of the file.
+        store::write_profile(&layers, &KEYBOARD_DIR, name, value).await?;
 
         // Reload if the keyboard profile is part of the active global profile
         let info = Profile::get_active_profile_info()?;
         if info.leds.iter().any(|prof| prof.profile == name) {
-            let info = Profile::load();
-            for handle in &self.handles {
-                let profile = info
-                    .leds
-                    .iter()
-                    .find_map(|(info, profile)| {
-                        if info == &handle.info {
-                            Some(profile.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or_else(|| ColorProfile::default(handle.info.mode));
-
-                handle.profile_sender.send(profile).await.unwrap();
-            }
+            self.reload().await?;
         }
         Ok(())
     }
 
-    async fn get_profile(&self, name: &str) -> fdo::Result<String> {
-        util::read_file(KEYBOARD_DIR, name).await
+    async fn get_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        store::read_profile(&layers, &KEYBOARD_DIR, name).await
+    }
+
+    /// The names of the visible profiles.
+    async fn list_profiles(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<Vec<String>> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        layers.names(&KEYBOARD_DIR).await
     }
 
-    async fn list_profiles(&self) -> fdo::Result<Vec<String>> {
-        util::get_profiles(KEYBOARD_DIR).await
+    /// The visible profiles as JSON list of [`ProfileEntry`](tailor_api::ProfileEntry).
+    async fn list_profile_entries(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        let entries = layers.list(&KEYBOARD_DIR).await?;
+        Ok(serde_json::to_string(&entries).unwrap())
     }
 
-    async fn remove_profile(&self, name: &str) -> fdo::Result<()> {
-        util::remove_file(KEYBOARD_DIR, name).await
+    /// Fails with `com.tux.Tailor.Error.ProfileInUse` if a global profile uses the profile.
+    async fn remove_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> Result<(), StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        self.remove(&layers, name, &RemovalStrategy::Refuse).await
     }
 
-    async fn rename_profile(&self, from: &str, to: &str) -> fdo::Result<Vec<String>> {
-        if self.list_profiles().await?.contains(&to.to_string()) {
-            Err(fdo::Error::InvalidArgs(format!(
-                "File `{to}` already exists"
-            )))
+    /// Remove the profile and update the global profiles that use it
+    /// according to the [`RemovalStrategy`] (JSON).
+    async fn remove_profile_with(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        strategy: &str,
+    ) -> Result<(), StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        let strategy: RemovalStrategy = serde_json::from_str(strategy)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        self.remove(&layers, name, &strategy).await
+    }
+
+    /// The global profiles that use the profile.
+    async fn list_dependents(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<Vec<String>> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        store::dependents(&layers, |info| {
+            info.leds.iter().flatten().any(|led| led.profile == name)
+        })
+        .await
+    }
+
+    async fn rename_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<String>, StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        if layers.names(&KEYBOARD_DIR).await?.contains(&to.to_string()) {
+            Err(fdo::Error::InvalidArgs(format!("File `{to}` already exists")).into())
         } else {
-            let profiles = util::get_profiles(PROFILE_DIR).await?;
+            layers.ensure_writable(&KEYBOARD_DIR, from)?;
+            store::ensure_no_user_dependents(&layers, &KEYBOARD_DIR, from, |info| {
+                info.leds.iter().flatten().any(|led| led.profile == from)
+            })?;
+            let profile_dir = layers.writable_dir(&PROFILE_DIR)?;
+
+            // Update the references and the file together.
+            // Global profiles of lower layers can't be changed.
+            let mut transaction = Transaction::default();
+            let profiles = layers.list(&PROFILE_DIR).await?;
 
             for profile in profiles {
-                let mut data =
-                    if let Ok(data) = util::read_json::<ProfileInfo>(PROFILE_DIR, &profile).await {
-                        data
-                    } else {
-                        continue;
-                    };
+                if profile.origin != layers.writable() {
+                    continue;
+                }
+                let mut data = if let Ok(data) = store::read_profile_json::<ProfileDefinition>(
+                    &layers,
+                    &PROFILE_DIR,
+                    &profile.name,
+                )
+                .await
+                {
+                    data
+                } else {
+                    continue;
+                };
                 let mut changed = false;
 
-                for led in &mut data.leds {
+                for led in data.leds.iter_mut().flatten() {
                     if led.profile == from {
                         to.clone_into(&mut led.profile);
                         changed = true;
@@ -79,20 +281,84 @@ impl LedInterface {
                 }
 
                 if changed {
-                    util::write_json(PROFILE_DIR, &profile, &data).await?;
+                    transaction
+                        .write_json(&profile_dir, &profile.name, &data)
+                        .await?;
                 }
             }
 
-            util::move_file(KEYBOARD_DIR, from, to).await?;
+            transaction.rename(&layers.writable_dir(&KEYBOARD_DIR)?, from, to)?;
+            transaction.commit().await?;
+
+            Ok(layers.names(&KEYBOARD_DIR).await?)
+        }
+    }
+
+    /// Set the brightness of all LED devices in percent.
+    /// The brightness is not stored in the active profile.
+    async fn set_brightness(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        brightness: u8,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::OverrideHardware).await?;
+        if brightness > 100 {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Brightness must be a percentage from 0 to 100: `{brightness}`"
+            )));
+        }
 
-            self.list_profiles().await
+        for handle in self.handles.get() {
+            handle
+                .brightness_sender
+                .send(brightness)
+                .await
+                .map_err(|err| fdo::Error::Failed(format!("Internal error: `{err}`")))?;
         }
+        Ok(())
     }
 
-    async fn override_color(&mut self, color: &str) -> fdo::Result<()> {
+    /// Read the brightness in percent.
+    /// If the devices differ, the highest brightness is returned.
+    async fn get_brightness(&self) -> fdo::Result<u8> {
+        self.handles
+            .get()
+            .iter()
+            .map(|handle| *handle.brightness_state.borrow())
+            .max()
+            .ok_or_else(|| fdo::Error::Failed("No LED devices available".to_owned()))
+    }
+
+    /// Emitted with the new brightness in percent whenever
+    /// the value returned by `get_brightness` changes.
+    #[zbus(signal)]
+    async fn brightness_changed(emitter: &SignalEmitter<'_>, brightness: u8) -> zbus::Result<()>;
+
+    /// Emitted with the [`LedDeviceInfo`](tailor_api::LedDeviceInfo)
+    /// as JSON when an LED device was plugged in.
+    #[zbus(signal)]
+    async fn device_added(emitter: &SignalEmitter<'_>, device: &str) -> zbus::Result<()>;
+
+    /// Emitted with the [`LedDeviceInfo`](tailor_api::LedDeviceInfo)
+    /// as JSON when an LED device was removed.
+    #[zbus(signal)]
+    async fn device_removed(emitter: &SignalEmitter<'_>, device: &str) -> zbus::Result<()>;
+
+    /// Emitted when an LED profile was changed on disk.
+    #[zbus(signal)]
+    pub async fn profile_changed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+
+    async fn override_color(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        color: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::OverrideHardware).await?;
         let color: Color =
             serde_json::from_str(color).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
-        for handle in &self.handles {
+        for handle in self.handles.get() {
             handle
                 .color_sender
                 .send(color.clone())
@@ -101,4 +367,58 @@ impl LedInterface {
         }
         Ok(())
     }
+
+    /// Play a short pattern on all LED devices.
+    /// Afterwards, the color profiles continue where they were interrupted.
+    async fn flash(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        pattern: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::OverrideHardware).await?;
+        let pattern: FlashPattern = serde_json::from_str(pattern)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        if pattern.points.is_empty() {
+            return Err(fdo::Error::InvalidArgs(
+                "Flash pattern doesn't contain any colors".to_owned(),
+            ));
+        }
+        if pattern
+            .points
+            .iter()
+            .any(|point| point.transition_time == 0 && point.hold_time == 0)
+        {
+            return Err(fdo::Error::InvalidArgs(
+                "Every color of a flash pattern must take some time".to_owned(),
+            ));
+        }
+        if pattern.repetitions > MAX_FLASH_REPETITIONS {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Flash pattern is repeated {} times, the maximum is {MAX_FLASH_REPETITIONS}",
+                pattern.repetitions
+            )));
+        }
+        let duration = pattern.duration();
+        if duration > MAX_FLASH_DURATION_MS {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Flash pattern takes {duration}ms, the maximum is {MAX_FLASH_DURATION_MS}ms"
+            )));
+        }
+        let steps = runtime::flash_steps(&pattern);
+        if steps > MAX_FLASH_STEPS {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Flash pattern has {steps} steps, the maximum is {MAX_FLASH_STEPS}"
+            )));
+        }
+
+        for handle in self.handles.get() {
+            handle
+                .flash_sender
+                .send(pattern.clone())
+                .await
+                .map_err(|err| fdo::Error::Failed(format!("Internal error: `{err}`")))?;
+        }
+        Ok(())
+    }
 }
@@ -2,8 +2,12 @@ mod fan;
 mod led;
 mod performance;
 mod profiles;
+mod rules;
+mod schedule;
 
 pub use fan::FanInterface;
 pub use led::LedInterface;
 pub use performance::PerformanceInterface;
 pub use profiles::ProfileInterface;
+pub use rules::RuleInterface;
+pub use schedule::ScheduleInterface;
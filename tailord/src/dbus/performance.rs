@@ -1,6 +1,9 @@
-use zbus::{fdo, interface};
+use zbus::{fdo, interface, message::Header, Connection};
 
-use crate::performance::PerformanceProfileRuntimeHandle;
+use crate::{
+    auth::{self, Action},
+    performance::PerformanceProfileRuntimeHandle,
+};
 
 pub struct PerformanceInterface {
     pub handler: Option<PerformanceProfileRuntimeHandle>,
@@ -24,7 +27,13 @@ impl PerformanceInterface {
 impl PerformanceInterface {
     /// Temporarily override the performance profile. Please note that this will not survive a
     /// restart as the performance profile is handled by the overall profile configuration.
-    async fn set_profile(&mut self, name: &str) -> fdo::Result<()> {
+    async fn set_profile(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::OverrideHardware).await?;
         self.handler()?
             .profile_sender
             .send(name.to_string())
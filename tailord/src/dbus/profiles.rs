@@ -1,61 +1,343 @@
-use tailor_api::{ColorProfile, LedDeviceInfo, ProfileInfo};
-use zbus::{fdo, interface};
+use tailor_api::{
+    match_led_profiles, ConflictPolicy, LedMatch, LedProfile, ProfileBundle, ProfileDefinition,
+    ProfileKind, RevertCondition,
+};
+use zbus::{fdo, interface, message::Header, object_server::SignalEmitter, Connection};
 
 use crate::{
+    auth::{self, Action},
+    autoswitch::AutoSwitchHandle,
+    bundle, config,
     fancontrol::FanRuntimeHandle,
-    led::LedRuntimeHandle,
+    layers::Layers,
+    led::LedHandles,
     performance::PerformanceProfileRuntimeHandle,
     profiles::{Profile, PROFILE_DIR},
-    util,
+    state::State,
+    store::{self, StoreError},
+    watch::{ProfileChange, ProfileWatcher},
 };
 
+use super::{FanInterface, LedInterface};
+
+#[derive(Clone)]
 pub struct ProfileInterface {
     pub fan_handles: Vec<FanRuntimeHandle>,
-    pub led_handles: Vec<LedRuntimeHandle>,
+    pub led_handles: LedHandles,
     pub performance_profile_handle: Option<PerformanceProfileRuntimeHandle>,
+    pub auto_switch: AutoSwitchHandle,
+}
+
+impl ProfileInterface {
+    /// Send the active global profile to all runtimes.
+    pub async fn apply_active_profile(&mut self) -> fdo::Result<()> {
+        let profile = Profile::load();
+
+        for (idx, fan_handle) in self.fan_handles.iter().enumerate() {
+            let fan_profile = profile.fan_profile(idx, fan_handle.role);
+            fan_handle
+                .profile_sender
+                .send(fan_profile)
+                .await
+                .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        }
+
+        let led_settings = profile.led_settings(&self.led_handles.infos());
+        for (led_handle, settings) in self.led_handles.get().iter().zip(led_settings) {
+            led_handle
+                .settings_sender
+                .send(settings)
+                .await
+                .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        }
+
+        if let Some(perf_handle) = self.performance_profile_handle.as_mut() {
+            if let Some(performance_profile) = profile.performance_profile {
+                perf_handle
+                    .profile_sender
+                    .send(performance_profile.to_string())
+                    .await
+                    .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+                perf_handle.set_active_performance_profile(&performance_profile.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply profiles that were changed on disk and emit the change signals.
+    pub async fn watch_profiles(
+        mut self,
+        emitter: SignalEmitter<'static>,
+        mut watcher: ProfileWatcher,
+    ) {
+        while let Some(changes) = watcher.next_changes().await {
+            if let Err(err) = self.apply_changes(&emitter, changes).await {
+                tracing::warn!("Failed to apply changed profiles: `{err}`");
+            }
+        }
+        tracing::warn!("Stop watching for profile changes");
+    }
+
+    /// Send the changed profiles only to the runtimes that use them.
+    /// Invalid files are skipped, so the runtimes keep their previous settings.
+    async fn apply_changes(
+        &mut self,
+        emitter: &SignalEmitter<'_>,
+        changes: Vec<ProfileChange>,
+    ) -> fdo::Result<()> {
+        let active_name = Profile::get_active_profile_name().await.ok();
+        // Changes of base profiles affect the active profile as well.
+        let active_chain = Profile::get_active_profile_chain();
+        let mut reload_all = false;
+        let mut changed_fans = Vec::new();
+        let mut changed_leds = Vec::new();
+
+        for change in changes {
+            if let Err(err) = change.validate() {
+                tracing::warn!("Ignoring invalid profile change {change:?}: `{err}`");
+                continue;
+            }
+            match change {
+                ProfileChange::Active => reload_all = true,
+                // The daemon applies its own changes of the state right away.
+                ProfileChange::State => reload_all |= State::reload_changed(),
+                ProfileChange::Profile { kind, name, .. } => {
+                    tracing::info!("{kind:?} profile `{name}` was changed on disk");
+                    match kind {
+                        ProfileKind::Global => {
+                            reload_all |= active_chain.contains(&name);
+                            Self::profile_changed(emitter, &name).await?;
+                        }
+                        ProfileKind::Led => {
+                            LedInterface::profile_changed(emitter, &name).await?;
+                            changed_leds.push(name);
+                        }
+                        ProfileKind::Fan => {
+                            FanInterface::profile_changed(emitter, &name).await?;
+                            changed_fans.push(name);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !reload_all && changed_fans.is_empty() && changed_leds.is_empty() {
+            return Ok(());
+        }
+        let info = Profile::get_active_profile_info().map_err(|err| {
+            fdo::Error::InvalidFileContent(format!("Keeping the previous profile: {err}"))
+        })?;
+
+        if reload_all {
+            self.apply_active_profile().await?;
+            if let Some(name) = active_name {
+                Self::active_profile_changed(emitter, &name).await?;
+            }
+            return Ok(());
+        }
+
+        let profile = Profile::load();
+        for (idx, fan_handle) in self.fan_handles.iter().enumerate() {
+            if info
+                .fan_profile(idx, fan_handle.role)
+                .is_some_and(|name| changed_fans.contains(name))
+            {
+                fan_handle
+                    .profile_sender
+                    .send(profile.fan_profile(idx, fan_handle.role))
+                    .await
+                    .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+            }
+        }
+
+        let entries: Vec<LedProfile> = profile
+            .leds
+            .iter()
+            .map(|(entry, _)| entry.clone())
+            .collect();
+        let assignments = match_led_profiles(&entries, &self.led_handles.infos());
+        for (led_handle, assignment) in self.led_handles.get().iter().zip(assignments) {
+            if let Some((idx, _)) = assignment {
+                if changed_leds.contains(&entries[idx].profile) {
+                    led_handle
+                        .settings_sender
+                        .send(profile.leds[idx].1.clone())
+                        .await
+                        .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[interface(name = "com.tux.Tailor.Profiles")]
 impl ProfileInterface {
-    async fn add_profile(&self, name: &str, value: &str) -> fdo::Result<()> {
-        // Verify correctness of the file.
-        serde_json::from_str::<ProfileInfo>(value)
-            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+    async fn add_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        value: &str,
+    ) -> fdo::Result<()> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        // Verifies correctness of the file.
+        store::write_profile(&layers, &PROFILE_DIR, name, value).await
+    }
 
-        util::write_file(PROFILE_DIR, name, value.as_bytes()).await
+    /// The profile with the fields it inherits from its base profiles.
+    async fn get_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        let info = store::read_resolved_profile(&layers, name)?;
+        Ok(serde_json::to_string(&info).unwrap())
     }
 
-    async fn get_profile(&self, name: &str) -> fdo::Result<String> {
-        util::read_file(PROFILE_DIR, name).await
+    /// The [`ProfileDefinition`](tailor_api::ProfileDefinition) as it's stored,
+    /// which only contains the fields that override its base profile.
+    async fn get_profile_definition(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        store::read_profile(&layers, &PROFILE_DIR, name).await
     }
 
-    async fn list_profiles(&self) -> fdo::Result<Vec<String>> {
-        util::get_profiles(PROFILE_DIR).await
+    /// The names of the visible profiles.
+    async fn list_profiles(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<Vec<String>> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        layers.names(&PROFILE_DIR).await
     }
 
-    async fn remove_profile(&self, name: &str) -> fdo::Result<()> {
-        util::remove_file(PROFILE_DIR, name).await
+    /// The visible profiles as JSON list of [`ProfileEntry`](tailor_api::ProfileEntry).
+    async fn list_profile_entries(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        let entries = layers.list(&PROFILE_DIR).await?;
+        Ok(serde_json::to_string(&entries).unwrap())
     }
 
-    async fn rename_profile(&mut self, from: &str, to: &str) -> fdo::Result<Vec<String>> {
-        if self.list_profiles().await?.contains(&to.to_string()) {
-            Err(fdo::Error::InvalidArgs(format!(
-                "File `{to}` already exists"
-            )))
+    /// Profiles that inherit from the removed profile keep their settings.
+    async fn remove_profile(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> Result<(), StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        store::remove_global_profile(&layers, name).await
+    }
+
+    async fn rename_profile(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<String>, StoreError> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        if layers.names(&PROFILE_DIR).await?.contains(&to.to_string()) {
+            Err(fdo::Error::InvalidArgs(format!("File `{to}` already exists")).into())
         } else {
-            util::move_file(PROFILE_DIR, from, to).await?;
+            store::rename_global_profile(&layers, from, to).await?;
 
-            if self.get_active_profile_name().await? == from {
-                self.set_active_profile_name(to).await?;
-                self.reload().await?;
+            let active_renamed =
+                State::update(|state| Ok(state.rename_profile(&layers, from, to))).await?;
+            if active_renamed {
+                self.apply_active_profile().await?;
             }
 
-            self.list_profiles().await
+            Ok(layers.names(&PROFILE_DIR).await?)
+        }
+    }
+
+    async fn set_active_profile_name(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::SwitchProfile).await?;
+        let layers = Layers::for_caller(connection, &header).await?;
+        Profile::activate_manually(&layers, name).await
+    }
+
+    /// Whether the active profile was activated manually,
+    /// which pauses the schedules and process rules.
+    async fn is_active_profile_locked(&self) -> fdo::Result<bool> {
+        Ok(State::load().locked)
+    }
+
+    /// Let schedules and process rules switch the profile again.
+    async fn clear_profile_lock(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::SwitchProfile).await?;
+        let was_locked = State::update(|state| Ok(std::mem::take(&mut state.locked))).await?;
+        if was_locked {
+            self.auto_switch.notify_changed();
         }
+        Ok(())
+    }
+
+    /// Activate a global profile until the [`RevertCondition`] given as JSON is met.
+    /// Afterwards, the previous profile is activated again.
+    async fn activate_profile_temporarily(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        until: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::SwitchProfile).await?;
+        let until: RevertCondition =
+            serde_json::from_str(until).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        let layers = Layers::for_caller(connection, &header).await?;
+        Profile::activate_temporarily(&layers, name, until).await?;
+        self.auto_switch.notify_changed();
+        self.apply_active_profile().await
     }
 
-    async fn set_active_profile_name(&self, name: &str) -> fdo::Result<()> {
-        Profile::set_active_profile_name(name).await
+    /// The [`TemporaryProfile`](tailor_api::TemporaryProfile) as JSON,
+    /// or `null` if the active profile isn't temporary.
+    async fn get_temporary_profile(&self) -> fdo::Result<String> {
+        let temporary = State::load().temporary.map(|temporary| temporary.info());
+        Ok(serde_json::to_string(&temporary).unwrap())
+    }
+
+    /// End the temporary profile early and activate the previous profile again.
+    async fn cancel_temporary_profile(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::SwitchProfile).await?;
+        if let Some(name) = State::update(|state| Ok(state.end_temporary())).await? {
+            self.auto_switch.notify_changed();
+            self.apply_active_profile().await?;
+            Self::temporary_profile_ended(&emitter, &name).await?;
+        }
+        Ok(())
     }
 
     async fn get_active_profile_name(&self) -> fdo::Result<String> {
@@ -66,54 +348,136 @@ impl ProfileInterface {
         Ok(self.fan_handles.len() as u8)
     }
 
+    /// The role of each fan in the order of the fans as JSON list.
+    /// Fans with an unknown role are `null`.
+    async fn get_fan_roles(&self) -> fdo::Result<String> {
+        let roles: Vec<_> = self.fan_handles.iter().map(|handle| handle.role).collect();
+        Ok(serde_json::to_string(&roles).unwrap())
+    }
+
     async fn get_led_devices(&self) -> fdo::Result<String> {
-        let devices: Vec<LedDeviceInfo> = self
-            .led_handles
-            .iter()
-            .map(|handle| handle.info.clone())
-            .collect();
-        Ok(serde_json::to_string(&devices).unwrap())
+        Ok(serde_json::to_string(&self.led_handles.infos()).unwrap())
     }
 
-    async fn reload(&mut self) -> fdo::Result<()> {
-        let Profile {
-            fans,
-            leds,
-            performance_profile,
-        } = Profile::load();
+    /// LED entries of all global profiles that don't match
+    /// a detected device exactly, as JSON list of profile name and entry.
+    async fn list_unmatched_led_entries(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        let devices = self.led_handles.infos();
 
-        for (idx, fan_handle) in self.fan_handles.iter().enumerate() {
-            let profile = fans.get(idx).cloned().unwrap_or_default();
-            fan_handle
-                .profile_sender
-                .send(profile)
-                .await
-                .map_err(|err| fdo::Error::Failed(err.to_string()))?;
-        }
+        let mut unmatched: Vec<(String, LedProfile)> = Vec::new();
+        for name in layers.names(&PROFILE_DIR).await? {
+            // Inherited entries are reported for the base profile only.
+            let info: ProfileDefinition =
+                match store::read_profile_json(&layers, &PROFILE_DIR, &name).await {
+                    Ok(info) => info,
+                    Err(err) => {
+                        tracing::warn!("Failed to parse profile `{name}`: {err}");
+                        continue;
+                    }
+                };
 
-        for led_handle in &self.led_handles {
-            let profile = match leds.get(&led_handle.info).cloned() {
-                Some(color_profile) => color_profile,
-                None => ColorProfile::default(led_handle.info.mode),
-            };
-            led_handle
-                .profile_sender
-                .send(profile)
-                .await
-                .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+            let leds = info.leds.unwrap_or_default();
+            let mut matched = vec![false; leds.len()];
+            for (idx, _) in match_led_profiles(&leds, &devices)
+                .into_iter()
+                .flatten()
+                .filter(|(_, quality)| *quality == LedMatch::Exact)
+            {
+                matched[idx] = true;
+            }
+            unmatched.extend(
+                leds.into_iter()
+                    .zip(matched)
+                    .filter(|(_, matched)| !matched)
+                    .map(|(entry, _)| (name.clone(), entry)),
+            );
         }
+        Ok(serde_json::to_string(&unmatched).unwrap())
+    }
 
-        if let Some(perf_handle) = self.performance_profile_handle.as_mut() {
-            if let Some(performance_profile) = performance_profile {
-                perf_handle
-                    .profile_sender
-                    .send(performance_profile.to_string())
-                    .await
-                    .map_err(|err| fdo::Error::Failed(err.to_string()))?;
-                perf_handle.set_active_performance_profile(&performance_profile.to_string());
-            }
+    /// A global profile and all profiles it references as JSON bundle.
+    async fn export_bundle(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        let bundle = bundle::export(&layers, name).await?;
+        Ok(serde_json::to_string(&bundle).unwrap())
+    }
+
+    /// Import a JSON bundle and return the name of the imported global profile.
+    async fn import_bundle(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        data: &str,
+        conflict_policy: &str,
+    ) -> fdo::Result<String> {
+        let layers = Layers::for_caller(connection, &header).await?;
+        auth::authorize(connection, &header, layers.edit_action()).await?;
+        let bundle: ProfileBundle =
+            serde_json::from_str(data).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        let policy: ConflictPolicy = serde_json::from_str(conflict_policy)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+
+        let name = bundle::import(&layers, bundle, policy).await?;
+
+        // Overwritten profiles might be in use.
+        if policy == ConflictPolicy::Overwrite {
+            self.apply_active_profile().await?;
         }
+        Ok(name)
+    }
 
-        Ok(())
+    /// Emitted when a global profile was changed on disk.
+    #[zbus(signal)]
+    async fn profile_changed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+
+    /// Emitted when another global profile was activated
+    /// or the active profile was changed on disk.
+    #[zbus(signal)]
+    async fn active_profile_changed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+
+    /// Emitted once per minute while a temporary profile with a duration
+    /// is active, with the remaining seconds.
+    #[zbus(signal)]
+    pub async fn temporary_profile_countdown(
+        emitter: &SignalEmitter<'_>,
+        name: &str,
+        remaining: u64,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when a temporary profile ended and the previous profile was activated again.
+    #[zbus(signal)]
+    pub async fn temporary_profile_ended(
+        emitter: &SignalEmitter<'_>,
+        name: &str,
+    ) -> zbus::Result<()>;
+
+    async fn reload(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::SwitchProfile).await?;
+        self.apply_active_profile().await
+    }
+
+    /// Load `tailord.toml` again and apply the active profile with the new settings.
+    async fn reload_config(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::EditProfiles).await?;
+        config::reload().map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))?;
+        self.apply_active_profile().await
     }
 }
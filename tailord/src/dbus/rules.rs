@@ -0,0 +1,60 @@
+use zbus::{fdo, interface, message::Header, Connection};
+
+use crate::{
+    auth::{self, Action},
+    autoswitch::AutoSwitchHandle,
+    rules::{self, RULE_DIR},
+    state::{State, Trigger},
+    util,
+};
+
+pub struct RuleInterface {
+    pub handle: AutoSwitchHandle,
+}
+
+#[interface(name = "com.tux.Tailor.Rules")]
+impl RuleInterface {
+    /// Add or replace a [`ProcessRule`](tailor_api::ProcessRule) as JSON.
+    async fn add_rule(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        value: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::EditProfiles).await?;
+        rules::write_rule(name, value).await?;
+        self.handle.notify_changed();
+        Ok(())
+    }
+
+    async fn get_rule(&self, name: &str) -> fdo::Result<String> {
+        let rule = rules::read_rule(name).await?;
+        Ok(serde_json::to_string(&rule).unwrap())
+    }
+
+    async fn list_rules(&self) -> fdo::Result<Vec<String>> {
+        Ok(util::get_profiles(&RULE_DIR).await.unwrap_or_default())
+    }
+
+    async fn remove_rule(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::EditProfiles).await?;
+        util::remove_file(&RULE_DIR, name).await?;
+        self.handle.notify_changed();
+        Ok(())
+    }
+
+    /// The name of the rule that activated the current profile,
+    /// or an empty string if no rule is active.
+    async fn get_active_rule(&self) -> fdo::Result<String> {
+        match State::load().automatic.map(|automatic| automatic.trigger) {
+            Some(Trigger::Rule(name)) => Ok(name),
+            _ => Ok(String::new()),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use zbus::{fdo, interface, message::Header, Connection};
+
+use crate::{
+    auth::{self, Action},
+    autoswitch::AutoSwitchHandle,
+    schedule::{self, SCHEDULE_DIR},
+    state::{State, Trigger},
+    util,
+};
+
+pub struct ScheduleInterface {
+    pub handle: AutoSwitchHandle,
+}
+
+#[interface(name = "com.tux.Tailor.Schedule")]
+impl ScheduleInterface {
+    /// Add or replace a [`ProfileSchedule`](tailor_api::ProfileSchedule) as JSON.
+    async fn add_schedule(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+        value: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::EditProfiles).await?;
+        schedule::write_schedule(name, value).await?;
+        self.handle.notify_changed();
+        Ok(())
+    }
+
+    async fn get_schedule(&self, name: &str) -> fdo::Result<String> {
+        let schedule = schedule::read_schedule(name).await?;
+        Ok(serde_json::to_string(&schedule).unwrap())
+    }
+
+    async fn list_schedules(&self) -> fdo::Result<Vec<String>> {
+        Ok(util::get_profiles(&SCHEDULE_DIR).await.unwrap_or_default())
+    }
+
+    async fn remove_schedule(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: &str,
+    ) -> fdo::Result<()> {
+        auth::authorize(connection, &header, Action::EditProfiles).await?;
+        util::remove_file(&SCHEDULE_DIR, name).await?;
+        self.handle.notify_changed();
+        Ok(())
+    }
+
+    /// The name of the schedule that activated the current profile,
+    /// or an empty string if no schedule is active.
+    async fn get_active_schedule(&self) -> fdo::Result<String> {
+        match State::load().automatic.map(|automatic| automatic.trigger) {
+            Some(Trigger::Schedule(name)) => Ok(name),
+            _ => Ok(String::new()),
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::{sync::Arc, time::Duration};
 
+use tailor_api::FanRole;
 use tokio::sync::{broadcast, mpsc};
-use tuxedo_ioctl::hal::traits::HardwareDevice;
+use tuxedo_ioctl::hal::{self, traits::HardwareDevice};
 
 use crate::suspend::get_suspend_receiver;
 
@@ -13,6 +14,8 @@ mod runtime;
 
 #[derive(Clone)]
 pub struct FanRuntimeHandle {
+    /// The role of the fan if the hardware family is known.
+    pub role: Option<FanRole>,
     pub fan_speed_sender: mpsc::Sender<u8>,
     pub profile_sender: mpsc::Sender<FanProfile>,
 }
@@ -32,6 +35,14 @@ pub struct FanRuntimeData {
     suspend_receiver: broadcast::Receiver<bool>,
 }
 
+/// The role of a fan as detected by the hardware interface.
+pub fn fan_role(io: &dyn HardwareDevice, fan_idx: u8) -> Option<FanRole> {
+    io.get_fan_role(fan_idx).map(|role| match role {
+        hal::FanRole::Cpu => FanRole::Cpu,
+        hal::FanRole::Gpu => FanRole::Gpu,
+    })
+}
+
 pub struct FanRuntime {
     profile_receiver: mpsc::Receiver<FanProfile>,
     fan_speed_receiver: mpsc::Receiver<u8>,
@@ -55,6 +66,7 @@ impl FanRuntime {
 
         (
             FanRuntimeHandle {
+                role: fan_role(io.as_ref(), fan_idx),
                 fan_speed_sender,
                 profile_sender,
             },
@@ -1,8 +1,10 @@
 use std::path::Path;
 
-use tailor_api::FanProfilePoint;
+use tailor_api::{FanProfilePoint, ProfileKind};
 use zbus::fdo;
 
+use crate::store;
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 #[serde(transparent)]
 pub struct FanProfile {
@@ -12,10 +14,8 @@ pub struct FanProfile {
 impl FanProfile {
     pub fn load_config(file_name: impl AsRef<Path>) -> fdo::Result<Self> {
         let file_name = file_name.as_ref();
-        let content =
-            std::fs::read(file_name).map_err(|err| fdo::Error::IOError(err.to_string()))?;
-        let mut inner: Vec<FanProfilePoint> = serde_json::from_slice(&content)
-            .map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))?;
+        let mut inner: Vec<FanProfilePoint> =
+            store::read_profile_json_sync(ProfileKind::Fan, file_name)?;
 
         if inner.is_empty() {
             return Err(fdo::Error::FileNotFound("Empty configuration".to_string()));
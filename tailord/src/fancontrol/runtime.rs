@@ -1,4 +1,7 @@
-use crate::suspend::process_suspend;
+use crate::{
+    config::{self, FanConfig},
+    suspend::process_suspend,
+};
 
 use super::{buffer::TemperatureBuffer, FanRuntimeData};
 
@@ -27,7 +30,7 @@ impl FanRuntimeData {
                 self.fan_speed.saturating_sub(fan_increment)
             });
 
-            let delay = suitable_delay(&self.temp_history, fan_diff);
+            let delay = suitable_delay(&self.temp_history, fan_diff, &config::fan());
 
             tracing::debug!(
                 "Fan {}: Current temperature is {current_temp}°C, fan speed: {}%, target fan speed: {target_fan_speed} \
@@ -45,7 +48,7 @@ impl FanRuntimeData {
 }
 
 /// Calculate a suitable delay to reduce CPU usage.
-fn suitable_delay(temp_buffer: &TemperatureBuffer, fan_diff: u8) -> Duration {
+fn suitable_delay(temp_buffer: &TemperatureBuffer, fan_diff: u8, config: &FanConfig) -> Duration {
     // How much is the temperature changing?
     let temperature_pressure = temp_buffer.diff_to_min_in_history();
 
@@ -57,37 +60,38 @@ fn suitable_delay(temp_buffer: &TemperatureBuffer, fan_diff: u8) -> Duration {
         .saturating_add(fan_diff_pressure)
         .min(15);
 
-    // Define a falling exponential function with time constant -1/7.
+    // Define a falling exponential function with time constant -1/decay.
     // This should yield decent results but the formula might be tuned
-    // to perform better.
+    // to perform better. With the default configuration:
     // 0  -> 2000ms
     // 15 -> ~230ms
-    const TAU: f64 = -1.0 / 7.0;
-    let delay = 2000.0 * (pressure as f64 * TAU).exp();
+    let tau = -1.0 / config.delay_decay;
+    let delay = config.max_delay_ms as f64 * (pressure as f64 * tau).exp();
     Duration::from_millis(delay as u64)
 }
 
 #[cfg(test)]
 mod test {
-    use crate::fancontrol::buffer::TemperatureBuffer;
+    use crate::{config::Config, fancontrol::buffer::TemperatureBuffer};
 
     use super::suitable_delay;
 
     #[test]
     fn test_suitable_delay() {
         let mut temp_buffer = TemperatureBuffer::new(20);
+        let config = Config::default().fan;
 
         // Test with no pressure.
-        assert_eq!(suitable_delay(&temp_buffer, 0).as_millis(), 2000);
+        assert_eq!(suitable_delay(&temp_buffer, 0, &config).as_millis(), 2000);
 
         // Test with max pressure.
-        assert_eq!(suitable_delay(&temp_buffer, 255).as_millis(), 234);
+        assert_eq!(suitable_delay(&temp_buffer, 255, &config).as_millis(), 234);
 
         // Test with pressure 1.
-        assert_eq!(suitable_delay(&temp_buffer, 2).as_millis(), 1733);
+        assert_eq!(suitable_delay(&temp_buffer, 2, &config).as_millis(), 1733);
 
         // Test with pressure 1 but this time through temperature diff.
         temp_buffer.update(21);
-        assert_eq!(suitable_delay(&temp_buffer, 0).as_millis(), 1733);
+        assert_eq!(suitable_delay(&temp_buffer, 0, &config).as_millis(), 1733);
     }
 }
@@ -0,0 +1,262 @@
+use std::path::{Component, Path};
+
+use once_cell::sync::Lazy;
+use tailor_api::{ProfileEntry, ProfileOrigin};
+use zbus::{fdo, message::Header, Connection};
+
+use crate::{
+    auth::{self, Action},
+    config, util,
+};
+
+/// Read-only profiles shipped by the distribution.
+static VENDOR_ROOT: Lazy<String> = Lazy::new(|| config::get().paths.vendor_path(""));
+
+/// Profiles managed by the administrator.
+static SYSTEM_ROOT: Lazy<String> = Lazy::new(|| config::get().paths.system_path(""));
+
+/// Profiles owned by individual users, stored in sub-directories named after their UID.
+/// User profiles are only enabled if this directory exists.
+static USER_ROOT: Lazy<String> = Lazy::new(|| config::get().paths.system_path("users/"));
+
+/// The profile layers that are visible to a caller.
+///
+/// Profiles are looked up in the layer of the user first, then in the
+/// system layer and finally in the vendor layer. Changes are always
+/// written to the highest layer, so a profile of a lower layer is
+/// shadowed by an edited copy instead of being modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layers {
+    user: Option<u32>,
+}
+
+impl Layers {
+    /// The layers used by the daemon itself and by root.
+    pub const SYSTEM: Self = Self { user: None };
+
+    pub fn for_user(uid: u32) -> Self {
+        if uid == 0 || !Path::new(USER_ROOT.as_str()).is_dir() {
+            Self::SYSTEM
+        } else {
+            Self { user: Some(uid) }
+        }
+    }
+
+    /// The layers of the sender of a D-Bus message.
+    pub async fn for_caller(connection: &Connection, header: &Header<'_>) -> fdo::Result<Self> {
+        Ok(Self::for_user(auth::caller_uid(connection, header).await?))
+    }
+
+    /// The layers that contain the profile file at `path`.
+    pub fn for_path(path: &Path) -> Self {
+        let uid = path.strip_prefix(USER_ROOT.as_str()).ok().and_then(|path| {
+            match path.components().next() {
+                Some(Component::Normal(uid)) => uid.to_str()?.parse().ok(),
+                _ => None,
+            }
+        });
+        match uid {
+            Some(uid) => Self { user: Some(uid) },
+            None => Self::SYSTEM,
+        }
+    }
+
+    /// The user whose profiles are visible in addition to the system and vendor profiles.
+    pub fn user(&self) -> Option<u32> {
+        self.user
+    }
+
+    /// The layer that new and changed profiles are written to.
+    pub fn writable(&self) -> ProfileOrigin {
+        match self.user {
+            Some(uid) => ProfileOrigin::User(uid),
+            None => ProfileOrigin::System,
+        }
+    }
+
+    /// The action that is needed to change the profiles of the writable layer.
+    /// Only the shared system layer requires an administrator.
+    pub fn edit_action(&self) -> Action {
+        match self.user {
+            Some(_) => Action::EditOwnProfiles,
+            None => Action::EditProfiles,
+        }
+    }
+
+    /// All layers from the highest to the lowest precedence.
+    fn origins(&self) -> impl Iterator<Item = ProfileOrigin> {
+        self.user
+            .map(ProfileOrigin::User)
+            .into_iter()
+            .chain([ProfileOrigin::System, ProfileOrigin::Vendor])
+    }
+
+    /// The directory of `base_path` in the writable layer.
+    /// The directory is created if necessary.
+    pub fn writable_dir(&self, base_path: &str) -> fdo::Result<String> {
+        let dir = dir(self.writable(), base_path);
+        std::fs::create_dir_all(&dir).map_err(|err| fdo::Error::IOError(err.to_string()))?;
+        Ok(dir)
+    }
+
+    /// Find the layer and the path of a profile.
+    pub fn resolve(&self, base_path: &str, name: &str) -> fdo::Result<(ProfileOrigin, String)> {
+        for origin in self.origins() {
+            let path = util::normalize_json_path(&dir(origin, base_path), name)?;
+            if Path::new(&path).is_file() {
+                return Ok((origin, path));
+            }
+        }
+        Err(fdo::Error::FileNotFound(format!(
+            "Couldn't find profile `{name}`"
+        )))
+    }
+
+    /// Fail if the profile isn't stored in the writable layer.
+    pub fn ensure_writable(&self, base_path: &str, name: &str) -> fdo::Result<()> {
+        let (origin, _) = self.resolve(base_path, name)?;
+        if origin == self.writable() {
+            Ok(())
+        } else {
+            Err(fdo::Error::AccessDenied(format!(
+                "Profile `{name}` is provided by the {origin:?} layer and can't be changed"
+            )))
+        }
+    }
+
+    /// All visible profiles with the layer they are loaded from.
+    pub async fn list(&self, base_path: &str) -> fdo::Result<Vec<ProfileEntry>> {
+        let mut layers = Vec::new();
+        for origin in self.origins() {
+            // Missing directories are empty layers.
+            let names = util::get_profiles(&dir(origin, base_path))
+                .await
+                .unwrap_or_default();
+            layers.push((origin, names));
+        }
+        Ok(merge(layers))
+    }
+
+    /// The names of all visible profiles.
+    pub async fn names(&self, base_path: &str) -> fdo::Result<Vec<String>> {
+        Ok(self
+            .list(base_path)
+            .await?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect())
+    }
+}
+
+/// The path of a profile directory relative to the system root,
+/// for example `fan` or `users/1000/fan`.
+pub fn relative_dir(dir: &str) -> &str {
+    dir.strip_prefix(SYSTEM_ROOT.as_str())
+        .unwrap_or(dir)
+        .trim_matches('/')
+}
+
+/// The directory of `base_path` in a layer.
+/// `base_path` is one of the profile directories of the system layer.
+pub fn dir(origin: ProfileOrigin, base_path: &str) -> String {
+    let sub_dir = base_path
+        .strip_prefix(SYSTEM_ROOT.as_str())
+        .unwrap_or(base_path);
+    match origin {
+        ProfileOrigin::Vendor => format!("{}{sub_dir}", *VENDOR_ROOT),
+        ProfileOrigin::System => base_path.to_owned(),
+        ProfileOrigin::User(uid) => format!("{}{uid}/{sub_dir}", *USER_ROOT),
+    }
+}
+
+/// The users that have a layer of their own.
+pub fn user_ids() -> Vec<u32> {
+    let Ok(entries) = std::fs::read_dir(USER_ROOT.as_str()) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str().and_then(|uid| uid.parse().ok()))
+        .collect()
+}
+
+/// The directories of `base_path` in all writable layers,
+/// that is the system layer and the layers of all users.
+pub fn writable_dirs(base_path: &str) -> Vec<String> {
+    let mut dirs = vec![base_path.to_owned()];
+    dirs.extend(
+        user_ids()
+            .into_iter()
+            .map(|uid| dir(ProfileOrigin::User(uid), base_path)),
+    );
+    dirs
+}
+
+/// Combine the profile names of multiple layers, ordered from the highest
+/// to the lowest precedence. Shadowed profiles are left out.
+fn merge(layers: Vec<(ProfileOrigin, Vec<String>)>) -> Vec<ProfileEntry> {
+    let mut entries: Vec<ProfileEntry> = Vec::new();
+    for (origin, names) in layers {
+        for name in names {
+            if !entries.iter().any(|entry| entry.name == name) {
+                entries.push(ProfileEntry { name, origin });
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use tailor_api::{ProfileEntry, ProfileOrigin};
+
+    use super::{dir, merge, Layers};
+
+    #[test]
+    fn layer_precedence() {
+        let entries = merge(vec![
+            (ProfileOrigin::User(1000), vec!["silent".to_owned()]),
+            (
+                ProfileOrigin::System,
+                vec!["silent".to_owned(), "default".to_owned()],
+            ),
+            (
+                ProfileOrigin::Vendor,
+                vec!["default".to_owned(), "gaming".to_owned()],
+            ),
+        ]);
+        let entry = |name: &str, origin| ProfileEntry {
+            name: name.to_owned(),
+            origin,
+        };
+        assert_eq!(
+            entries,
+            [
+                entry("default", ProfileOrigin::System),
+                entry("gaming", ProfileOrigin::Vendor),
+                entry("silent", ProfileOrigin::User(1000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn layer_paths() {
+        let base_path = "/etc/tailord/fan/";
+        assert_eq!(
+            dir(ProfileOrigin::Vendor, base_path),
+            "/usr/share/tailord/fan/"
+        );
+        assert_eq!(
+            dir(ProfileOrigin::User(1000), base_path),
+            "/etc/tailord/users/1000/fan/"
+        );
+
+        let path = Path::new("/etc/tailord/users/1000/profiles/silent.json");
+        assert_eq!(Layers::for_path(path), Layers { user: Some(1000) });
+        let path = Path::new("/usr/share/tailord/profiles/default.json");
+        assert_eq!(Layers::for_path(path), Layers::SYSTEM);
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A timebase that is shared by all LED runtimes.
+///
+/// Color animations calculate their current color from this clock,
+/// so multiple devices with the same profile stay in sync.
+#[derive(Debug, Clone)]
+pub struct AnimationClock {
+    state: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Debug)]
+struct ClockState {
+    epoch: Instant,
+    /// The elapsed time when the clock was paused
+    /// and the number of pauses that are still running.
+    paused: Option<(Duration, usize)>,
+}
+
+impl ClockState {
+    fn elapsed(&self, now: Instant) -> Duration {
+        match self.paused {
+            Some((elapsed, _)) => elapsed,
+            None => now.saturating_duration_since(self.epoch),
+        }
+    }
+}
+
+impl Default for AnimationClock {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ClockState {
+                epoch: Instant::now(),
+                paused: None,
+            })),
+        }
+    }
+}
+
+impl AnimationClock {
+    /// The time since the start of the clock.
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed(Instant::now())
+    }
+
+    /// Stop the clock until the returned guard is dropped.
+    /// This is used to continue animations where they were
+    /// after they were interrupted, for example by a flash pattern.
+    /// The clock continues when all overlapping pauses ended.
+    pub fn pause(&self) -> ClockPause {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = state.elapsed(Instant::now());
+        match &mut state.paused {
+            Some((_, count)) => *count += 1,
+            None => state.paused = Some((elapsed, 1)),
+        }
+        ClockPause {
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Keeps the [`AnimationClock`] stopped while it exists.
+#[derive(Debug)]
+pub struct ClockPause {
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl Drop for ClockPause {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some((elapsed, count)) = &mut state.paused {
+            *count -= 1;
+            if *count == 0 {
+                state.epoch = Instant::now() - *elapsed;
+                state.paused = None;
+            }
+        }
+    }
+}
+
+/// Find the step of an animation at the given position.
+///
+/// `step_ends` contains the time in ms at which each step ends, relative
+/// to the start of the animation. Returns the index of the step and
+/// the remaining time of the step in ms.
+pub fn animation_step(step_ends: &[u64], position: u64) -> (usize, u64) {
+    let idx = step_ends
+        .partition_point(|end| *end <= position)
+        .min(step_ends.len() - 1);
+    (idx, step_ends[idx].saturating_sub(position))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{animation_step, AnimationClock};
+
+    #[test]
+    fn step_lookup() {
+        let step_ends = [100, 300, 600];
+
+        assert_eq!(animation_step(&step_ends, 0), (0, 100));
+        assert_eq!(animation_step(&step_ends, 99), (0, 1));
+        assert_eq!(animation_step(&step_ends, 100), (1, 200));
+        assert_eq!(animation_step(&step_ends, 599), (2, 1));
+    }
+
+    #[test]
+    fn paused_clock() {
+        let clock = AnimationClock::default();
+        std::thread::sleep(Duration::from_millis(10));
+        let first = clock.pause();
+        let second = clock.pause();
+
+        let elapsed = clock.elapsed();
+        assert!(elapsed >= Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(10));
+        drop(first);
+        assert_eq!(clock.elapsed(), elapsed);
+
+        drop(second);
+        std::thread::sleep(Duration::from_millis(10));
+        let resumed = clock.elapsed();
+        assert!(resumed >= elapsed + Duration::from_millis(10));
+        assert!(resumed < elapsed + Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,115 @@
+use std::{io, sync::Arc, time::Duration};
+
+use tailor_api::{ColorProfile, LedDeviceInfo};
+use tuxedo_ioctl::hal::traits::HardwareDevice;
+use tuxedo_sysfs::led::{Collection, HotplugEvent, HotplugMonitor};
+
+use crate::profiles::Profile;
+
+use super::{device_info, AnimationClock, LedHandles, LedRuntime, LedRuntimeData, SystemSampler};
+
+/// Devices with multiple zones add one LED node per zone.
+/// Wait for the remaining nodes so the zones can be grouped.
+const HOTPLUG_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// A device that was added or removed while the daemon is running.
+#[derive(Debug, Clone)]
+pub enum LedDeviceChange {
+    Added(LedDeviceInfo),
+    Removed(LedDeviceInfo),
+}
+
+/// Starts and stops LED runtimes when LED devices are plugged in or removed.
+pub struct LedHotplug {
+    monitor: HotplugMonitor,
+    handles: LedHandles,
+    io: Option<Arc<dyn HardwareDevice>>,
+    clock: AnimationClock,
+}
+
+impl LedHotplug {
+    pub fn new(
+        handles: LedHandles,
+        io: Option<Arc<dyn HardwareDevice>>,
+        clock: AnimationClock,
+    ) -> Result<Self, io::Error> {
+        Ok(Self {
+            monitor: HotplugMonitor::new()?,
+            handles,
+            io,
+            clock,
+        })
+    }
+
+    /// Wait for LED devices to be added or removed and update the runtimes.
+    /// Returns [`None`] if the hotplug events can't be received anymore.
+    pub async fn next_changes(&mut self) -> Option<Vec<LedDeviceChange>> {
+        let mut events = vec![self.monitor.next_event().await?];
+        tokio::time::sleep(HOTPLUG_SETTLE_TIME).await;
+        while let Some(event) = self.monitor.try_next_event() {
+            events.push(event);
+        }
+
+        let mut changes = Vec::new();
+        let mut added = Vec::new();
+        for event in events {
+            match event {
+                HotplugEvent::Added(node) => {
+                    if !self.handles.contains_node(&node) && !added.contains(&node) {
+                        added.push(node);
+                    }
+                }
+                HotplugEvent::Removed(node) => {
+                    added.retain(|name| name != &node);
+                    if let Some(handle) = self.handles.remove_node(&node) {
+                        tracing::info!("LED device `{}` was removed", handle.info.device_id());
+                        changes.push(LedDeviceChange::Removed(handle.info));
+                    }
+                }
+            }
+        }
+
+        if !added.is_empty() {
+            changes.extend(self.start_runtimes(&added).await);
+        }
+        Some(changes)
+    }
+
+    async fn start_runtimes(&self, nodes: &[String]) -> Vec<LedDeviceChange> {
+        let controllers = match Collection::from_nodes(nodes).await {
+            Ok(collection) => collection.into_inner(),
+            Err(err) => {
+                tracing::warn!("Failed to load new LED devices {nodes:?}: `{err}`");
+                return Vec::new();
+            }
+        };
+
+        // Match the profile entries together with the existing devices,
+        // so the new devices don't take entries that are already in use.
+        let profile = Profile::load();
+        let mut infos = self.handles.infos();
+        let existing = infos.len();
+        infos.extend(controllers.iter().map(device_info));
+        let settings = profile.led_settings(&infos).split_off(existing);
+
+        let mut changes = Vec::new();
+        for (controller, settings) in controllers.into_iter().zip(settings) {
+            let mut data = LedRuntimeData {
+                controller,
+                profile: ColorProfile::None,
+                sampler: SystemSampler::new(self.io.clone()),
+                clock: self.clock.clone(),
+                phase_offset: 0,
+            };
+            data.apply_settings(settings).await;
+
+            let (handle, runtime) = LedRuntime::new(data);
+            tracing::info!("LED device `{}` was added", handle.info.device_id());
+            changes.push(LedDeviceChange::Added(handle.info.clone()));
+
+            tokio_uring::spawn(runtime.run());
+            self.handles.insert(handle);
+        }
+        changes
+    }
+}
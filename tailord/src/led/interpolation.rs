@@ -0,0 +1,243 @@
+use tailor_api::{Color, ColorInterpolation};
+
+/// The number of segments used to measure the length of a path.
+const PATH_SEGMENTS: u32 = 32;
+
+/// Interpolate between two colors.
+/// `progress` goes from 0.0 (`from`) to 1.0 (`to`).
+pub fn interpolate(
+    from: &Color,
+    to: &Color,
+    progress: f64,
+    interpolation: ColorInterpolation,
+) -> Color {
+    let rgb = interpolate_rgb(to_f64(from), to_f64(to), progress, interpolation);
+    let [r, g, b] = rgb.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color { r, g, b }
+}
+
+/// The length of the path that [`interpolate`] takes from `from` to `to`.
+/// It is measured in sRGB values, so paths of different color spaces are
+/// comparable. The way around the hue circle is longer than the direct way.
+pub fn path_length(from: &Color, to: &Color, interpolation: ColorInterpolation) -> f64 {
+    let from = to_f64(from);
+    let to = to_f64(to);
+
+    let mut length = 0.0;
+    let mut prev = from;
+    for idx in 1..=PATH_SEGMENTS {
+        let progress = idx as f64 / PATH_SEGMENTS as f64;
+        let point =
+            interpolate_rgb(from, to, progress, interpolation).map(|value| value.clamp(0.0, 1.0));
+        let square_sum: f64 = (0..3).map(|idx| (point[idx] - prev[idx]).powi(2)).sum();
+        length += square_sum.sqrt();
+        prev = point;
+    }
+    length * 255.0
+}
+
+fn interpolate_rgb(
+    from: [f64; 3],
+    to: [f64; 3],
+    progress: f64,
+    interpolation: ColorInterpolation,
+) -> [f64; 3] {
+    match interpolation {
+        ColorInterpolation::Srgb => lerp3(from, to, progress),
+        ColorInterpolation::LinearRgb => {
+            let mixed = lerp3(from.map(srgb_to_linear), to.map(srgb_to_linear), progress);
+            mixed.map(linear_to_srgb)
+        }
+        ColorInterpolation::Hsv => hsv_to_rgb(lerp_hsv(rgb_to_hsv(from), rgb_to_hsv(to), progress)),
+        ColorInterpolation::Oklab => {
+            let mixed = lerp3(rgb_to_oklab(from), rgb_to_oklab(to), progress);
+            oklab_to_rgb(mixed)
+        }
+    }
+}
+
+fn to_f64(color: &Color) -> [f64; 3] {
+    [color.r, color.g, color.b].map(|value| value as f64 / 255.0)
+}
+
+fn lerp(from: f64, to: f64, progress: f64) -> f64 {
+    from + (to - from) * progress
+}
+
+fn lerp3(from: [f64; 3], to: [f64; 3], progress: f64) -> [f64; 3] {
+    [
+        lerp(from[0], to[0], progress),
+        lerp(from[1], to[1], progress),
+        lerp(from[2], to[2], progress),
+    ]
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Returns hue in degrees, saturation and value.
+fn rgb_to_hsv([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta <= f64::EPSILON {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max <= f64::EPSILON {
+        0.0
+    } else {
+        delta / max
+    };
+
+    [hue, saturation, max]
+}
+
+fn hsv_to_rgb([hue, saturation, value]: [f64; 3]) -> [f64; 3] {
+    let chroma = value * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let [r, g, b] = match sector as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    let offset = value - chroma;
+    [r + offset, g + offset, b + offset]
+}
+
+/// Interpolate along the shorter way around the hue circle.
+fn lerp_hsv(mut from: [f64; 3], mut to: [f64; 3], progress: f64) -> [f64; 3] {
+    // Gray colors have no meaningful hue, so use the hue of the other color.
+    if from[1] <= f64::EPSILON {
+        from[0] = to[0];
+    } else if to[1] <= f64::EPSILON {
+        to[0] = from[0];
+    }
+
+    let mut hue_diff = to[0] - from[0];
+    if hue_diff > 180.0 {
+        hue_diff -= 360.0;
+    } else if hue_diff < -180.0 {
+        hue_diff += 360.0;
+    }
+
+    [
+        (from[0] + hue_diff * progress).rem_euclid(360.0),
+        lerp(from[1], to[1], progress),
+        lerp(from[2], to[2], progress),
+    ]
+}
+
+// Conversion matrices from https://bottosson.github.io/posts/oklab/
+fn rgb_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_rgb([lightness, a, b]: [f64; 3]) -> [f64; 3] {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+    .map(|value| linear_to_srgb(value.max(0.0)))
+}
+
+#[cfg(test)]
+mod test {
+    use tailor_api::{Color, ColorInterpolation};
+
+    use super::{interpolate, path_length};
+
+    #[test]
+    fn interpolation_end_points() {
+        let from = Color { r: 255, g: 0, b: 0 };
+        let to = Color {
+            r: 0,
+            g: 40,
+            b: 255,
+        };
+
+        for interpolation in [
+            ColorInterpolation::Srgb,
+            ColorInterpolation::LinearRgb,
+            ColorInterpolation::Hsv,
+            ColorInterpolation::Oklab,
+        ] {
+            let start = interpolate(&from, &to, 0.0, interpolation);
+            let end = interpolate(&from, &to, 1.0, interpolation);
+            for (actual, expected) in [(start, &from), (end, &to)] {
+                assert!((actual.r as i16 - expected.r as i16).abs() <= 1);
+                assert!((actual.g as i16 - expected.g as i16).abs() <= 1);
+                assert!((actual.b as i16 - expected.b as i16).abs() <= 1);
+            }
+        }
+
+        // Red to blue through HSV passes magenta, not gray.
+        let mid = interpolate(
+            &Color { r: 255, g: 0, b: 0 },
+            &Color { r: 0, g: 0, b: 255 },
+            0.5,
+            ColorInterpolation::Hsv,
+        );
+        assert_eq!(
+            mid,
+            Color {
+                r: 255,
+                g: 0,
+                b: 255
+            }
+        );
+    }
+
+    #[test]
+    fn path_lengths() {
+        let red = Color { r: 255, g: 0, b: 0 };
+        let blue = Color { r: 0, g: 0, b: 255 };
+
+        let direct = path_length(&red, &blue, ColorInterpolation::Srgb);
+        assert!((direct - 255.0 * 2f64.sqrt()).abs() < 1e-6);
+
+        // Through magenta, first the blue and then the red channel changes.
+        let arc = path_length(&red, &blue, ColorInterpolation::Hsv);
+        assert!((arc - 510.0).abs() < 1e-6);
+
+        assert_eq!(path_length(&red, &red, ColorInterpolation::Oklab), 0.0);
+    }
+}
@@ -1,47 +1,205 @@
-use tailor_api::{Color, ColorProfile, LedDeviceInfo};
-use tokio::sync::mpsc;
-use tuxedo_sysfs::led::Controller;
+use std::sync::Arc;
+use tailor_api::{Color, ColorProfile, FlashPattern, LedControllerMode, LedDeviceInfo};
 
+use tokio::sync::{mpsc, watch};
+use tuxedo_sysfs::led::{BrightnessWatcher, Controller};
+
+mod clock;
+mod hotplug;
+mod interpolation;
+mod reactive;
 pub mod runtime;
 
+pub use clock::AnimationClock;
+pub use hotplug::{LedDeviceChange, LedHotplug};
+pub use reactive::{check_sorted_points, SystemSampler};
+
+/// Brightness in percent that is used if a profile doesn't specify one.
+pub const DEFAULT_BRIGHTNESS: u8 = 100;
+
+/// Gamma value used if gamma correction is enabled.
+const GAMMA: f32 = 2.2;
+
+/// The settings of an LED device that are stored in a profile.
+#[derive(Debug, Clone)]
+pub struct LedSettings {
+    pub profile: ColorProfile,
+    /// Brightness in percent.
+    pub brightness: u8,
+    pub gamma_correction: bool,
+    /// Delay of the color animation in ms.
+    pub phase_offset: u32,
+}
+
+impl LedSettings {
+    pub fn default(mode: LedControllerMode) -> Self {
+        Self {
+            profile: ColorProfile::default(mode),
+            brightness: DEFAULT_BRIGHTNESS,
+            gamma_correction: false,
+            phase_offset: 0,
+        }
+    }
+}
+
 pub struct LedRuntime {
     data: LedRuntimeData,
-    profile_receiver: mpsc::Receiver<ColorProfile>,
+    settings_receiver: mpsc::Receiver<LedSettings>,
     color_receiver: mpsc::Receiver<Color>,
+    flash_receiver: mpsc::Receiver<FlashPattern>,
+    brightness_receiver: mpsc::Receiver<u8>,
+    brightness_state: watch::Sender<u8>,
+    brightness_watcher: Option<BrightnessWatcher>,
 }
 
 pub struct LedRuntimeData {
     pub controller: Controller,
     pub profile: ColorProfile,
+    /// Provides the values for reactive color profiles.
+    pub sampler: SystemSampler,
+    /// The timebase of color animations.
+    pub clock: AnimationClock,
+    /// Delay of the color animation in ms.
+    pub phase_offset: u32,
+}
+
+impl LedRuntimeData {
+    /// Apply the settings of a profile to the device.
+    pub async fn apply_settings(&mut self, settings: LedSettings) {
+        let LedSettings {
+            profile,
+            brightness,
+            gamma_correction,
+            phase_offset,
+        } = settings;
+
+        self.profile = profile;
+        self.phase_offset = phase_offset;
+        self.controller.set_gamma(gamma_correction.then_some(GAMMA));
+        if let Err(err) = self.controller.set_brightness(brightness).await {
+            tracing::error!("Failed to update keyboard brightness: `{err}`");
+        }
+    }
 }
 
+pub fn device_info(controller: &Controller) -> LedDeviceInfo {
+    LedDeviceInfo {
+        device_name: controller.device_name.clone(),
+        function: controller.function.clone(),
+        mode: controller.mode(),
+        zones: controller.zone_count() as u32,
+        layout: controller.zone_layout(),
+        channels: controller.channels().to_vec(),
+    }
+}
+
+/// The runtime stops once all clones of its handle are dropped.
 #[derive(Clone)]
 pub struct LedRuntimeHandle {
     pub info: LedDeviceInfo,
-    pub profile_sender: mpsc::Sender<ColorProfile>,
+    /// The sysfs LED nodes that are controlled by the runtime.
+    pub nodes: Vec<String>,
+    pub settings_sender: mpsc::Sender<LedSettings>,
     pub color_sender: mpsc::Sender<Color>,
+    pub flash_sender: mpsc::Sender<FlashPattern>,
+    pub brightness_sender: mpsc::Sender<u8>,
+    /// The brightness in percent that is currently applied by the runtime.
+    pub brightness_state: watch::Receiver<u8>,
 }
 
 impl LedRuntime {
-    pub fn new(data: LedRuntimeData) -> (LedRuntimeHandle, Self) {
-        let (profile_sender, profile_receiver) = mpsc::channel(1);
+    pub fn new(mut data: LedRuntimeData) -> (LedRuntimeHandle, Self) {
+        let (settings_sender, settings_receiver) = mpsc::channel(1);
         let (color_sender, color_receiver) = mpsc::channel(1);
+        let (flash_sender, flash_receiver) = mpsc::channel(1);
+        let (brightness_sender, brightness_receiver) = mpsc::channel(1);
+        let (brightness_state, brightness_state_receiver) =
+            watch::channel(data.controller.brightness());
+        let brightness_watcher = data.controller.take_brightness_watcher();
 
         (
             LedRuntimeHandle {
-                info: LedDeviceInfo {
-                    device_name: data.controller.device_name.clone(),
-                    function: data.controller.function.clone(),
-                    mode: data.controller.mode(),
-                },
-                profile_sender,
+                info: device_info(&data.controller),
+                nodes: data
+                    .controller
+                    .node_names()
+                    .map(ToOwned::to_owned)
+                    .collect(),
+                settings_sender,
                 color_sender,
+                flash_sender,
+                brightness_sender,
+                brightness_state: brightness_state_receiver,
             },
             Self {
                 data,
-                profile_receiver,
+                settings_receiver,
                 color_receiver,
+                flash_receiver,
+                brightness_receiver,
+                brightness_state,
+                brightness_watcher,
             },
         )
     }
 }
+
+/// The handles of all running LED runtimes.
+///
+/// LED devices can be added and removed while the daemon
+/// is running, see [`LedHotplug`].
+#[derive(Clone)]
+pub struct LedHandles {
+    handles: Arc<watch::Sender<Vec<LedRuntimeHandle>>>,
+}
+
+impl LedHandles {
+    pub fn new(handles: Vec<LedRuntimeHandle>) -> Self {
+        Self {
+            handles: Arc::new(watch::Sender::new(handles)),
+        }
+    }
+
+    /// The handles of the currently available devices.
+    pub fn get(&self) -> Vec<LedRuntimeHandle> {
+        self.handles.borrow().clone()
+    }
+
+    pub fn infos(&self) -> Vec<LedDeviceInfo> {
+        self.handles
+            .borrow()
+            .iter()
+            .map(|handle| handle.info.clone())
+            .collect()
+    }
+
+    /// Get notified whenever a device is added or removed.
+    pub fn subscribe(&self) -> watch::Receiver<Vec<LedRuntimeHandle>> {
+        self.handles.subscribe()
+    }
+
+    /// Whether a runtime already controls the given LED node.
+    fn contains_node(&self, node: &str) -> bool {
+        self.handles
+            .borrow()
+            .iter()
+            .any(|handle| handle.nodes.iter().any(|name| name == node))
+    }
+
+    fn insert(&self, handle: LedRuntimeHandle) {
+        self.handles.send_modify(|handles| handles.push(handle));
+    }
+
+    /// Remove the handle of the runtime that controls the given LED node.
+    fn remove_node(&self, node: &str) -> Option<LedRuntimeHandle> {
+        let mut removed = None;
+        self.handles.send_if_modified(|handles| {
+            let idx = handles
+                .iter()
+                .position(|handle| handle.nodes.iter().any(|name| name == node));
+            removed = idx.map(|idx| handles.remove(idx));
+            removed.is_some()
+        });
+        removed
+    }
+}
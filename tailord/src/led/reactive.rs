@@ -0,0 +1,200 @@
+use std::{io, sync::Arc, time::Duration};
+
+use tailor_api::{Color, ColorProfile, ReactiveProfile, ReactiveSource};
+use tuxedo_ioctl::hal::traits::HardwareDevice;
+
+use super::interpolation::interpolate;
+
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+const PROC_STAT_PATH: &str = "/proc/stat";
+
+/// The CPU load of the first sample is measured over this interval.
+const CPU_PRIME_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reads the system values that reactive color profiles are based on.
+#[derive(Debug)]
+pub struct SystemSampler {
+    io: Option<Arc<dyn HardwareDevice>>,
+    /// Busy and total CPU time of the previous sample.
+    prev_cpu_times: Option<(u64, u64)>,
+}
+
+impl SystemSampler {
+    pub fn new(io: Option<Arc<dyn HardwareDevice>>) -> Self {
+        Self {
+            io,
+            prev_cpu_times: None,
+        }
+    }
+
+    /// Sample a value. Blocking reads run on the blocking
+    /// thread pool, so they don't stall the LED runtimes.
+    pub async fn sample(&mut self, source: ReactiveSource) -> Result<u8, io::Error> {
+        match source {
+            ReactiveSource::Temperature => self.temperature().await,
+            ReactiveSource::Battery => unblock(battery_level).await,
+            ReactiveSource::CpuLoad => self.cpu_load().await,
+        }
+    }
+
+    async fn temperature(&self) -> Result<u8, io::Error> {
+        let io = self.io.clone().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "No hardware interface available")
+        })?;
+        unblock(move || {
+            io.get_fan_temperature(0)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+        })
+        .await
+    }
+
+    /// CPU load since the previous sample.
+    async fn cpu_load(&mut self) -> Result<u8, io::Error> {
+        if self.prev_cpu_times.is_none() {
+            // Otherwise, the first sample would be the load since boot.
+            self.prev_cpu_times = Some(read_cpu_times().await?);
+            tokio::time::sleep(CPU_PRIME_INTERVAL).await;
+        }
+
+        let (busy, total) = read_cpu_times().await?;
+        let (prev_busy, prev_total) = self.prev_cpu_times.replace((busy, total)).unwrap_or((0, 0));
+        let busy_diff = busy.saturating_sub(prev_busy);
+        let total_diff = total.saturating_sub(prev_total);
+
+        let load = (busy_diff * 100)
+            .checked_div(total_diff)
+            .unwrap_or_default();
+        Ok(load.min(100) as u8)
+    }
+}
+
+/// Run a blocking function on the blocking thread pool.
+async fn unblock<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, io::Error> + Send + 'static,
+) -> Result<T, io::Error> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?
+}
+
+async fn read_cpu_times() -> Result<(u64, u64), io::Error> {
+    let content = tokio::fs::read_to_string(PROC_STAT_PATH).await?;
+    parse_cpu_times(&content)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid format of /proc/stat"))
+}
+
+/// Check that the points of all reactive profiles are sorted by value.
+pub fn check_sorted_points(profile: &ColorProfile) -> Result<(), String> {
+    match profile {
+        ColorProfile::Reactive(reactive) => {
+            if reactive
+                .points
+                .windows(2)
+                .all(|points| points[0].value <= points[1].value)
+            {
+                Ok(())
+            } else {
+                Err("The points of a reactive profile must be sorted by value".to_owned())
+            }
+        }
+        ColorProfile::Zones(zones) => zones.iter().try_for_each(check_sorted_points),
+        ColorProfile::None | ColorProfile::Single(_) | ColorProfile::Multiple(_) => Ok(()),
+    }
+}
+
+/// Level of the first battery in percent.
+fn battery_level() -> Result<u8, io::Error> {
+    for entry in std::fs::read_dir(POWER_SUPPLY_PATH)? {
+        let path = entry?.path();
+        let is_battery = std::fs::read_to_string(path.join("type"))
+            .map(|ty| ty.trim() == "Battery")
+            .unwrap_or(false);
+
+        if is_battery {
+            let capacity = std::fs::read_to_string(path.join("capacity"))?;
+            return capacity
+                .trim()
+                .parse::<u8>()
+                .map(|capacity| capacity.min(100))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "No battery found"))
+}
+
+/// Parse the busy and total CPU time from the first line of `/proc/stat`.
+fn parse_cpu_times(content: &str) -> Option<(u64, u64)> {
+    let line = content.lines().next()?;
+    let mut fields = line.split_whitespace();
+    if fields.next()? != "cpu" {
+        return None;
+    }
+
+    let values: Vec<u64> = fields
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+    // idle and iowait
+    let idle = values.get(3)? + values.get(4).copied().unwrap_or_default();
+    // Guest times are already included in the user times.
+    let total: u64 = values.iter().take(8).sum();
+    Some((total - idle, total))
+}
+
+/// Calculate the color of the gradient at the given value.
+pub fn gradient_color(profile: &ReactiveProfile, value: u8) -> Color {
+    let points = &profile.points;
+    let Some(first) = points.first() else {
+        return Color { r: 0, g: 0, b: 0 };
+    };
+
+    match points.iter().position(|point| point.value >= value) {
+        None => points.last().unwrap().color.clone(),
+        Some(0) => first.color.clone(),
+        Some(idx) => {
+            let low = &points[idx - 1];
+            let high = &points[idx];
+            let progress = (value - low.value) as f64 / (high.value - low.value) as f64;
+            interpolate(&low.color, &high.color, progress, profile.interpolation)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tailor_api::{Color, ColorProfile, ReactiveProfile};
+
+    use super::{check_sorted_points, gradient_color, parse_cpu_times};
+
+    #[test]
+    fn gradient() {
+        let profile = ReactiveProfile::default();
+
+        assert_eq!(gradient_color(&profile, 20), Color { r: 0, g: 0, b: 255 });
+        assert_eq!(gradient_color(&profile, 40), Color { r: 0, g: 0, b: 255 });
+        assert_eq!(
+            gradient_color(&profile, 65),
+            Color {
+                r: 128,
+                g: 0,
+                b: 128
+            }
+        );
+        assert_eq!(gradient_color(&profile, 90), Color { r: 255, g: 0, b: 0 });
+        assert_eq!(gradient_color(&profile, 100), Color { r: 255, g: 0, b: 0 });
+
+        let mut unsorted = profile.clone();
+        unsorted.points.reverse();
+        assert!(check_sorted_points(&ColorProfile::Reactive(profile)).is_ok());
+        assert!(
+            check_sorted_points(&ColorProfile::Zones(vec![ColorProfile::Reactive(unsorted)]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn cpu_times() {
+        let content = "cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 50 0 25 400 25 0 0 0 0 0\n";
+        assert_eq!(parse_cpu_times(content), Some((150, 1000)));
+        assert_eq!(parse_cpu_times("intr 1 2 3"), None);
+    }
+}
@@ -1,11 +1,33 @@
-use std::{future::pending, time::Duration};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    future::pending,
+    time::Duration,
+};
 
-use tailor_api::{Color, ColorPoint, ColorProfile, ColorTransition};
+use tailor_api::{
+    Color, ColorInterpolation, ColorPoint, ColorProfile, ColorTransition, FlashPattern,
+    ReactiveProfile,
+};
 use tokio::sync::broadcast;
+use tuxedo_sysfs::led::BrightnessWatcher;
 
-use crate::suspend::{get_suspend_receiver, process_suspend};
+use crate::{
+    config,
+    suspend::{get_suspend_receiver, process_suspend},
+};
 
-use super::{LedRuntime, LedRuntimeData};
+use super::{
+    clock::animation_step,
+    interpolation::{interpolate, path_length},
+    reactive::gradient_color,
+    LedRuntime, LedRuntimeData,
+};
+
+/// Interval for checking brightness changes made by the firmware.
+const HW_BRIGHTNESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Interval for sampling the system values of reactive color profiles.
+const REACTIVE_SAMPLE_INTERVAL: Duration = Duration::from_millis(1000);
 
 impl LedRuntime {
     pub async fn run(mut self) {
@@ -13,9 +35,14 @@ impl LedRuntime {
 
         loop {
             tokio::select! {
-                new_colors = self.profile_receiver.recv() => {
-                    if let Some(colors) = new_colors {
-                        self.data.profile = colors;
+                new_settings = self.settings_receiver.recv() => {
+                    if let Some(settings) = new_settings {
+                        self.data.apply_settings(settings).await;
+                        self.brightness_state.send_replace(self.data.controller.brightness());
+                    } else {
+                        // All handles were dropped, e.g. because the device was removed.
+                        tracing::debug!("Stopping LED runtime of `{}`", self.data.controller.device_name());
+                        break;
                     }
                 }
                 // Override the current color value for 1s
@@ -37,18 +64,54 @@ impl LedRuntime {
                         }
                     }
                 }
+                flash = self.flash_receiver.recv() => {
+                    if let Some(pattern) = flash {
+                        self.data.play_flash(&pattern).await;
+                    }
+                }
+                new_brightness = self.brightness_receiver.recv() => {
+                    if let Some(brightness) = new_brightness {
+                        if let Err(err) = self.data.controller.set_brightness(brightness).await {
+                            tracing::error!("Failed to update keyboard brightness: `{err}`");
+                        }
+                        self.brightness_state.send_replace(self.data.controller.brightness());
+                    }
+                }
+                hw_brightness = wait_for_hw_brightness(&mut self.brightness_watcher) => {
+                    tracing::debug!("Firmware changed the brightness to {hw_brightness}%");
+                    // The firmware already updated the device, so only the state needs to change.
+                    self.data.controller.sync_brightness(hw_brightness);
+                    self.brightness_state.send_replace(self.data.controller.brightness());
+                }
                 _ = self.data.update_colors(&mut suspend_receiver) => {}
             }
         }
     }
 }
 
+/// Wait until the firmware changes the brightness.
+/// Stops watching after an error.
+async fn wait_for_hw_brightness(watcher: &mut Option<BrightnessWatcher>) -> u8 {
+    if let Some(inner) = watcher {
+        match inner.wait_for_change(HW_BRIGHTNESS_POLL_INTERVAL).await {
+            Ok(brightness) => return brightness,
+            Err(err) => {
+                tracing::warn!("Stop watching for brightness changes of the firmware: `{err}`");
+                *watcher = None;
+            }
+        }
+    }
+    pending().await
+}
+
 impl LedRuntimeData {
     pub async fn update_colors(&mut self, suspend_receiver: &mut broadcast::Receiver<bool>) {
         match &self.profile {
             ColorProfile::None => pending().await,
             ColorProfile::Single(color) => {
-                self.controller.set_color(color).await.unwrap();
+                if let Err(err) = self.controller.set_color(color).await {
+                    tracing::error!("Failed setting keyboard colors: `{err}`")
+                }
                 pending().await
             }
             ColorProfile::Multiple(colors) => {
@@ -56,27 +119,273 @@ impl LedRuntimeData {
                 self.run_color_animation(&color_steps, suspend_receiver)
                     .await;
             }
+            ColorProfile::Reactive(reactive) => {
+                let reactive = reactive.clone();
+                self.run_reactive(&reactive, suspend_receiver).await;
+            }
+            ColorProfile::Zones(zones) => {
+                let zones: Vec<ZoneColors> = zones.iter().map(ZoneColors::new).collect();
+                self.run_zones(&zones, suspend_receiver).await;
+            }
         }
     }
 
+    /// Position of the color animation based on the shared animation clock.
+    /// Returns the index of the current step and its remaining time in ms.
+    fn animation_position(&self, step_ends: &[u64]) -> (usize, u64) {
+        let cycle = step_ends.last().copied().unwrap_or_default().max(1);
+        let elapsed = self.clock.elapsed().as_millis() as u64;
+        let position = (elapsed + cycle - self.phase_offset as u64 % cycle) % cycle;
+        animation_step(step_ends, position)
+    }
+
     /// Infinitely run a color animation and
     /// stop the animation while suspended.
+    ///
+    /// The current step is calculated from the shared animation clock,
+    /// so all devices with the same profile and phase offset are in sync.
     async fn run_color_animation(
         &mut self,
         color_steps: &[(Color, u32)],
         suspend_receiver: &mut broadcast::Receiver<bool>,
     ) {
-        for step in color_steps.iter().cycle() {
-            if let Err(err) = self.controller.set_color(&step.0).await {
+        let step_ends = calculate_step_ends(color_steps);
+        let cycle = step_ends.last().copied().unwrap_or_default();
+        if cycle == 0 {
+            if let Some((color, _)) = color_steps.first() {
+                if let Err(err) = self.controller.set_color(color).await {
+                    tracing::error!("Failed setting keyboard colors: `{err}`")
+                }
+            }
+            return pending().await;
+        }
+
+        loop {
+            let (idx, remaining) = self.animation_position(&step_ends);
+
+            if let Err(err) = self.controller.set_color(&color_steps[idx].0).await {
                 tracing::error!("Failed setting keyboard colors: `{err}`")
             }
 
             tokio::select! {
-                _ = tokio::time::sleep(Duration::from_millis(step.1 as u64)) => {}
+                _ = tokio::time::sleep(Duration::from_millis(remaining)) => {}
                 _ = process_suspend(suspend_receiver) => {}
             }
         }
     }
+
+    /// Play a flash pattern and restore the previous color afterwards.
+    /// The animation clock is paused while the flash is playing,
+    /// so the color animations continue exactly where they were.
+    async fn play_flash(&mut self, pattern: &FlashPattern) {
+        if pattern.points.is_empty() {
+            return;
+        }
+
+        let _pause = self.clock.pause();
+        let prev_color = self.controller.get_color().await.ok();
+        let color_steps = calculate_color_animation_steps(&pattern.points);
+
+        for _ in 0..pattern.repetitions {
+            for (color, step_time) in &color_steps {
+                if let Err(err) = self.controller.set_color(color).await {
+                    tracing::error!("Failed setting keyboard colors: `{err}`")
+                }
+                tokio::time::sleep(Duration::from_millis(*step_time as u64)).await;
+            }
+        }
+
+        if let Some(color) = prev_color {
+            if let Err(err) = self.controller.set_color(&color).await {
+                tracing::error!("Failed setting keyboard colors: `{err}`")
+            }
+        }
+    }
+}
+
+impl LedRuntimeData {
+    /// Infinitely update the color based on the sampled
+    /// system value and pause while suspended.
+    async fn run_reactive(
+        &mut self,
+        profile: &ReactiveProfile,
+        suspend_receiver: &mut broadcast::Receiver<bool>,
+    ) {
+        let mut current_color = None;
+        let mut failed = false;
+
+        loop {
+            match self.sampler.sample(profile.source).await {
+                Ok(value) => {
+                    failed = false;
+                    let color = gradient_color(profile, value);
+                    if current_color.as_ref() != Some(&color) {
+                        if let Err(err) = self.controller.set_color(&color).await {
+                            tracing::error!("Failed setting keyboard colors: `{err}`")
+                        }
+                        current_color = Some(color);
+                    }
+                }
+                Err(err) => {
+                    // Only log the first of consecutive errors to avoid flooding the log.
+                    if !failed {
+                        tracing::warn!("Failed to sample {:?}: `{err}`", profile.source);
+                        failed = true;
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(REACTIVE_SAMPLE_INTERVAL) => {}
+                _ = process_suspend(suspend_receiver) => {
+                    // Colors might have been reset during suspend.
+                    current_color = None;
+                }
+            }
+        }
+    }
+}
+
+/// Calculates the colors of a single zone over time.
+enum ZoneColors {
+    Off,
+    Static(Color),
+    Animation {
+        color_steps: Vec<(Color, u32)>,
+        step_ends: Vec<u64>,
+    },
+    Reactive(ReactiveProfile),
+}
+
+impl ZoneColors {
+    fn new(profile: &ColorProfile) -> Self {
+        match profile {
+            ColorProfile::None => Self::Off,
+            ColorProfile::Single(color) => Self::Static(color.clone()),
+            ColorProfile::Multiple(colors) if colors.is_empty() => Self::Off,
+            ColorProfile::Multiple(colors) => {
+                let color_steps = calculate_color_animation_steps(colors);
+                let step_ends = calculate_step_ends(&color_steps);
+                if step_ends.last().copied().unwrap_or_default() == 0 {
+                    color_steps
+                        .into_iter()
+                        .next()
+                        .map_or(Self::Off, |(color, _)| Self::Static(color))
+                } else {
+                    Self::Animation {
+                        color_steps,
+                        step_ends,
+                    }
+                }
+            }
+            ColorProfile::Reactive(reactive) => Self::Reactive(reactive.clone()),
+            // Zones can't be nested, so use the profile of the first zone.
+            ColorProfile::Zones(zones) => zones.first().map_or(Self::Off, Self::new),
+        }
+    }
+}
+
+impl LedRuntimeData {
+    /// Infinitely update the colors of all zones and pause while suspended.
+    /// Zones without a profile are turned off.
+    async fn run_zones(
+        &mut self,
+        zones: &[ZoneColors],
+        suspend_receiver: &mut broadcast::Receiver<bool>,
+    ) {
+        let zone_count = self.controller.zone_count();
+        let mut current_colors: Vec<Option<Color>> = vec![None; zone_count];
+        let mut failed = false;
+
+        loop {
+            // Sample each source only once per update, because the CPU load
+            // depends on the time since the previous sample.
+            let mut samples = HashMap::new();
+            let mut next_update: Option<Duration> = None;
+
+            for (idx, current_color) in current_colors.iter_mut().enumerate() {
+                let (color, delay) = match zones.get(idx).unwrap_or(&ZoneColors::Off) {
+                    ZoneColors::Off => (Some(Color { r: 0, g: 0, b: 0 }), None),
+                    ZoneColors::Static(color) => (Some(color.clone()), None),
+                    ZoneColors::Animation {
+                        color_steps,
+                        step_ends,
+                    } => {
+                        let (step, remaining) = self.animation_position(step_ends);
+                        (
+                            Some(color_steps[step].0.clone()),
+                            Some(Duration::from_millis(remaining)),
+                        )
+                    }
+                    ZoneColors::Reactive(profile) => {
+                        if let Entry::Vacant(entry) = samples.entry(profile.source) {
+                            entry.insert(self.sampler.sample(profile.source).await);
+                        }
+                        let color = match &samples[&profile.source] {
+                            Ok(value) => Some(gradient_color(profile, *value)),
+                            Err(err) => {
+                                if !failed {
+                                    tracing::warn!(
+                                        "Failed to sample {:?}: `{err}`",
+                                        profile.source
+                                    );
+                                }
+                                None
+                            }
+                        };
+                        (color, Some(REACTIVE_SAMPLE_INTERVAL))
+                    }
+                };
+
+                if let Some(color) = color {
+                    if current_color.as_ref() != Some(&color) {
+                        if let Err(err) = self.controller.set_zone_color(idx, &color).await {
+                            tracing::error!("Failed setting keyboard colors: `{err}`")
+                        }
+                        *current_color = Some(color);
+                    }
+                }
+                if let Some(delay) = delay {
+                    next_update = Some(next_update.map_or(delay, |next| next.min(delay)));
+                }
+            }
+            // Only log the first of consecutive errors to avoid flooding the log.
+            failed = samples.values().any(Result::is_err);
+
+            let sleep = async {
+                match next_update {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => pending().await,
+                }
+            };
+            tokio::select! {
+                _ = sleep => {}
+                _ = process_suspend(suspend_receiver) => {
+                    // Colors might have been reset during suspend.
+                    current_colors.fill(None);
+                }
+            }
+        }
+    }
+}
+
+/// The number of colors that are set while playing a flash pattern.
+pub fn flash_steps(pattern: &FlashPattern) -> u64 {
+    if pattern.points.is_empty() {
+        return 0;
+    }
+    calculate_color_animation_steps(&pattern.points).len() as u64 * pattern.repetitions as u64
+}
+
+/// The time in ms at which each step of an animation ends.
+fn calculate_step_ends(color_steps: &[(Color, u32)]) -> Vec<u64> {
+    color_steps
+        .iter()
+        .scan(0, |end, (_, step_time)| {
+            *end += *step_time as u64;
+            Some(*end)
+        })
+        .collect()
 }
 
 fn calculate_color_animation_steps(colors: &[ColorPoint]) -> Vec<(Color, u32)> {
@@ -88,40 +397,66 @@ fn calculate_color_animation_steps(colors: &[ColorPoint]) -> Vec<(Color, u32)> {
             color,
             transition,
             transition_time,
+            hold_time,
+            interpolation,
         } = color_point.clone();
 
         match transition {
             ColorTransition::None => {
-                color_steps.push((color, transition_time));
+                color_steps.push((color.clone(), transition_time));
             }
-            ColorTransition::Linear => {
-                linear_color_transition(&mut color_steps, color, &prev_color, transition_time);
+            transition => {
+                color_transition(
+                    &mut color_steps,
+                    color.clone(),
+                    &prev_color,
+                    transition_time,
+                    &transition,
+                    interpolation,
+                );
             }
         }
 
+        if hold_time > 0 {
+            color_steps.push((color, hold_time));
+        }
+
         prev_color = color_point.color.clone();
     }
     color_steps
 }
 
-fn linear_color_transition(
+fn color_transition(
     color_steps: &mut Vec<(Color, u32)>,
     color: Color,
     prev_color: &Color,
     transition_time: u32,
+    transition: &ColorTransition,
+    interpolation: ColorInterpolation,
 ) {
-    // Max step size 80 ms (12.5 fps).
+    // Max step size 80 ms (12.5 fps) by default.
     // More would be rather CPU intensive for a background job.
-    let steps = transition_time / 80;
+    let steps = transition_time / config::led().min_frame_interval_ms;
 
     if steps == 0 {
         color_steps.push((color, transition_time));
     } else {
-        let r_diff = color.r as f64 - prev_color.r as f64;
-        let g_diff = color.g as f64 - prev_color.g as f64;
-        let b_diff = color.b as f64 - prev_color.b as f64;
+        // Size the steps from the way the color takes in the selected color space.
+        // A breathing pulse fades both colors to black and back.
+        let distance = if *transition == ColorTransition::Breathing {
+            let black = Color { r: 0, g: 0, b: 0 };
+            path_length(&black, prev_color, interpolation).max(path_length(
+                &black,
+                &color,
+                interpolation,
+            ))
+        } else {
+            path_length(prev_color, &color, interpolation)
+        };
 
-        let decent_steps = decent_linear_steps(transition_time, &[r_diff, g_diff, b_diff]);
+        // Curves change the color faster than a linear transition at their
+        // steepest point, so they need more steps to look equally smooth.
+        let decent_steps = decent_linear_steps(transition_time, distance * max_slope(transition));
 
         // Use a lower step size if the animation is slow.
         // The human eye won't notice the lower fps but
@@ -131,32 +466,74 @@ fn linear_color_transition(
         let step_time = transition_time / steps;
 
         for idx in 0..steps {
-            let percent = idx as f64 / steps as f64;
-
-            let r = f64_to_u8(prev_color.r as f64 + r_diff * percent);
-            let g = f64_to_u8(prev_color.g as f64 + g_diff * percent);
-            let b = f64_to_u8(prev_color.b as f64 + b_diff * percent);
-
-            let color = Color { r, g, b };
+            let progress = idx as f64 / steps as f64;
+            let color = if *transition == ColorTransition::Breathing {
+                breathing_color(prev_color, &color, progress, interpolation)
+            } else {
+                interpolate(
+                    prev_color,
+                    &color,
+                    ease(transition, progress),
+                    interpolation,
+                )
+            };
             color_steps.push((color, step_time));
         }
     }
 }
 
-fn f64_to_u8(float: f64) -> u8 {
-    float.clamp(0.0, 255.0).round() as u8
+/// The color of a breathing pulse from `from` to `to`.
+/// The brightness follows a cosine from full to dark and back.
+fn breathing_color(
+    from: &Color,
+    to: &Color,
+    progress: f64,
+    interpolation: ColorInterpolation,
+) -> Color {
+    let level = (1.0 + (progress * 2.0 * std::f64::consts::PI).cos()) / 2.0;
+    let color = if progress < 0.5 { from } else { to };
+    interpolate(&Color { r: 0, g: 0, b: 0 }, color, level, interpolation)
+}
+
+/// Map the progress of a transition (0.0 to 1.0)
+/// to the progress of the color change.
+fn ease(transition: &ColorTransition, progress: f64) -> f64 {
+    match transition {
+        ColorTransition::None => 1.0,
+        ColorTransition::Linear => progress,
+        ColorTransition::EaseIn => progress.powi(2),
+        ColorTransition::EaseOut => 1.0 - (1.0 - progress).powi(2),
+        ColorTransition::EaseInOut => {
+            if progress < 0.5 {
+                4.0 * progress.powi(3)
+            } else {
+                1.0 - (-2.0 * progress + 2.0).powi(3) / 2.0
+            }
+        }
+        // Breathing doesn't move from one color to the other, see `breathing_color`.
+        ColorTransition::Breathing => progress,
+    }
 }
 
-fn decent_linear_steps(transition_time: u32, diffs: &[f64]) -> u32 {
-    let diff_square_sum: f64 = diffs.iter().map(|diff| diff.powi(2)).sum();
-    let diff_rms = diff_square_sum.sqrt();
+/// The maximum slope of the easing curve compared to a linear transition.
+fn max_slope(transition: &ColorTransition) -> f64 {
+    match transition {
+        ColorTransition::None | ColorTransition::Linear => 1.0,
+        ColorTransition::EaseIn | ColorTransition::EaseOut => 2.0,
+        ColorTransition::EaseInOut => 3.0,
+        ColorTransition::Breathing => std::f64::consts::PI,
+    }
+}
 
-    if diff_rms <= f64::EPSILON {
+/// The number of steps for a linear transition over
+/// a distance of sRGB values that looks smooth.
+fn decent_linear_steps(transition_time: u32, distance: f64) -> u32 {
+    if distance <= f64::EPSILON {
         1
     } else {
         // A delta of 15 as rgb value per second should be barely
         // visible to the human eye.
-        let imperceivable_steps = diff_rms / 15.0;
+        let imperceivable_steps = distance / 15.0;
 
         // As time becomes larger, make smaller steps because they
         // might become identifiable as individual steps again.
@@ -168,23 +545,65 @@ fn decent_linear_steps(transition_time: u32, diffs: &[f64]) -> u32 {
 
 #[cfg(test)]
 mod test {
-    use crate::led::runtime::decent_linear_steps;
+    use tailor_api::{Color, ColorInterpolation, ColorTransition};
+
+    use crate::led::runtime::{breathing_color, decent_linear_steps, ease};
 
     #[test]
     fn decent_linear_step() {
-        let decent_steps = decent_linear_steps(1000, &[0.0]);
+        let decent_steps = decent_linear_steps(1000, 0.0);
         assert_eq!(decent_steps, 1);
 
-        let decent_steps = decent_linear_steps(1000, &[150.0]);
+        let decent_steps = decent_linear_steps(1000, 150.0);
         assert_eq!(decent_steps, 10);
 
-        let decent_steps = decent_linear_steps(3000, &[150.0]);
+        let decent_steps = decent_linear_steps(3000, 150.0);
         assert_eq!(decent_steps, 17);
 
-        let decent_steps = decent_linear_steps(1000, &[75.0]);
+        let decent_steps = decent_linear_steps(1000, 75.0);
         assert_eq!(decent_steps, 5);
 
-        let decent_steps = decent_linear_steps(100, &[75.0]);
+        let decent_steps = decent_linear_steps(100, 75.0);
         assert_eq!(decent_steps, 2);
     }
+
+    #[test]
+    fn easing_curves() {
+        for transition in [
+            ColorTransition::Linear,
+            ColorTransition::EaseIn,
+            ColorTransition::EaseOut,
+            ColorTransition::EaseInOut,
+        ] {
+            assert!(ease(&transition, 0.0).abs() < 1e-9);
+            assert!((ease(&transition, 1.0) - 1.0).abs() < 1e-9);
+
+            // Curves must never go backwards.
+            let mut prev = 0.0;
+            for idx in 1..=100 {
+                let value = ease(&transition, idx as f64 / 100.0);
+                assert!(value >= prev);
+                prev = value;
+            }
+        }
+
+        assert!(ease(&ColorTransition::EaseIn, 0.5) < 0.5);
+        assert!(ease(&ColorTransition::EaseOut, 0.5) > 0.5);
+        assert!((ease(&ColorTransition::EaseInOut, 0.5) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn breathing_pulse() {
+        let from = Color { r: 200, g: 0, b: 0 };
+        let to = Color { r: 0, g: 0, b: 200 };
+        let colors: Vec<Color> = [0.0, 0.25, 0.5, 0.75]
+            .into_iter()
+            .map(|progress| breathing_color(&from, &to, progress, ColorInterpolation::Srgb))
+            .collect();
+
+        assert_eq!(colors[0], from);
+        assert_eq!(colors[1], Color { r: 100, g: 0, b: 0 });
+        assert_eq!(colors[2], Color { r: 0, g: 0, b: 0 });
+        assert_eq!(colors[3], Color { r: 0, g: 0, b: 100 });
+    }
 }
@@ -1,31 +1,64 @@
+mod auth;
+mod autoswitch;
+mod bundle;
+mod config;
 mod dbus;
 mod fancontrol;
+mod layers;
 pub mod led;
 mod performance;
 mod profiles;
+mod rules;
+mod schedule;
 pub mod shutdown;
+mod state;
+mod store;
 mod suspend;
 pub mod util;
+mod watch;
 
-use std::future::pending;
+use std::{future::pending, path::PathBuf};
 
-use dbus::{FanInterface, PerformanceInterface, ProfileInterface};
+use clap::Parser;
+
+use dbus::{
+    FanInterface, PerformanceInterface, ProfileInterface, RuleInterface, ScheduleInterface,
+};
 use profiles::Profile;
 use tailor_api::{ColorProfile, LedControllerMode};
 use tuxedo_ioctl::hal::IoInterface;
 
 use crate::{
+    autoswitch::{AutoSwitchHandle, AutoSwitchRuntime},
     dbus::LedInterface,
     fancontrol::FanRuntime,
-    led::{LedRuntime, LedRuntimeData},
+    led::{
+        device_info, AnimationClock, LedHandles, LedHotplug, LedRuntime, LedRuntimeData,
+        SystemSampler,
+    },
     performance::PerformanceProfileRuntime,
     profiles::SupportedFeatures,
+    watch::ProfileWatcher,
 };
 
-const DBUS_NAME: &str = "com.tux.Tailor";
 const DBUS_PATH: &str = "/com/tux/Tailor";
 
+/// Daemon handling fan, keyboard and general hardware support for Tuxedo laptops
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The configuration file
+    #[arg(long, short, default_value = config::DEFAULT_CONFIG_PATH)]
+    config: PathBuf,
+
+    /// Override a key of the configuration file, for example `led.min_frame_interval_ms=40`
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = config::parse_override)]
+    overrides: Vec<(String, String)>,
+}
+
 fn main() {
+    let args = Args::parse();
+
     if std::env::var_os("RUST_LOG").is_none() {
         std::env::set_var("RUST_LOG", "info");
     }
@@ -36,6 +69,11 @@ fn main() {
         .without_time()
         .init();
 
+    if let Err(err) = config::init(args.config, args.overrides) {
+        tracing::error!("{err}");
+        std::process::exit(1);
+    }
+
     tokio_uring::start(start_runtime());
 }
 
@@ -82,11 +120,8 @@ async fn start_runtime() {
     if let Some(device) = &device {
         let available_fans = device.get_number_fans();
         for fan_idx in 0..available_fans {
-            let profile = profile
-                .fans
-                .get(fan_idx as usize)
-                .cloned()
-                .unwrap_or_default();
+            let role = fancontrol::fan_role(device.as_ref(), fan_idx);
+            let profile = profile.fan_profile(fan_idx as usize, role);
             let (handle, runtime) = FanRuntime::new(fan_idx, device.clone(), profile);
 
             fan_handles.push(handle);
@@ -94,32 +129,37 @@ async fn start_runtime() {
         }
     }
 
+    let animation_clock = AnimationClock::default();
     let mut led_handles = Vec::new();
     let mut led_runtimes = Vec::new();
-    for led_device in led_devices {
-        let profile = profile
-            .leds
-            .iter()
-            .find_map(|(info, profile)| {
-                if info.device_name == led_device.device_name
-                    && info.function == led_device.function
-                {
-                    Some(profile.clone())
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| ColorProfile::default(led_device.mode()));
-
-        let (handle, runtime) = LedRuntime::new(LedRuntimeData {
+    let led_infos: Vec<_> = led_devices.iter().map(device_info).collect();
+    let led_settings = profile.led_settings(&led_infos);
+    for (led_device, settings) in led_devices.into_iter().zip(led_settings) {
+        let mut data = LedRuntimeData {
             controller: led_device,
-            profile,
-        });
+            profile: ColorProfile::None,
+            sampler: SystemSampler::new(device.clone()),
+            clock: animation_clock.clone(),
+            phase_offset: 0,
+        };
+        data.apply_settings(settings).await;
+
+        let (handle, runtime) = LedRuntime::new(data);
 
         led_handles.push(handle);
         led_runtimes.push(runtime);
     }
 
+    let led_handles = LedHandles::new(led_handles);
+    let led_hotplug =
+        match LedHotplug::new(led_handles.clone(), device.clone(), animation_clock.clone()) {
+            Ok(hotplug) => Some(hotplug),
+            Err(err) => {
+                tracing::warn!("Failed to watch for LED devices: `{err}`");
+                None
+            }
+        };
+
     let (performance_profile_handle, performance_profile_runtime) = match device {
         Some(device) => {
             if let Ok(default_performance_profile) = device.get_default_odm_performance_profile() {
@@ -136,14 +176,33 @@ async fn start_runtime() {
         None => (None, None),
     };
 
+    let auto_switch_handle = AutoSwitchHandle::default();
     let profile_interface = ProfileInterface {
         led_handles: led_handles.clone(),
         fan_handles: fan_handles.clone(),
         performance_profile_handle: performance_profile_handle.clone(),
+        auto_switch: auto_switch_handle.clone(),
+    };
+
+    let profile_watcher = match ProfileWatcher::new() {
+        Ok(watcher) => Some((watcher, profile_interface.clone())),
+        Err(err) => {
+            tracing::warn!("Failed to watch for profile changes: `{err}`");
+            None
+        }
+    };
+
+    let auto_switch_runtime =
+        AutoSwitchRuntime::new(auto_switch_handle.clone(), profile_interface.clone());
+    let schedule_interface = ScheduleInterface {
+        handle: auto_switch_handle.clone(),
+    };
+    let rule_interface = RuleInterface {
+        handle: auto_switch_handle,
     };
 
     let led_interface = LedInterface {
-        handles: led_handles,
+        handles: led_handles.clone(),
     };
 
     let fan_interface = FanInterface {
@@ -154,10 +213,11 @@ async fn start_runtime() {
         handler: performance_profile_handle,
     };
 
-    tracing::debug!("Connecting to DBUS as {DBUS_NAME}");
-    let _conn = zbus::connection::Builder::system()
+    let dbus_name = config::get().dbus.name;
+    tracing::debug!("Connecting to DBUS as {dbus_name}");
+    let conn = zbus::connection::Builder::system()
         .unwrap()
-        .name(DBUS_NAME)
+        .name(dbus_name)
         .unwrap()
         .serve_at(DBUS_PATH, led_interface)
         .unwrap()
@@ -167,13 +227,37 @@ async fn start_runtime() {
         .unwrap()
         .serve_at(DBUS_PATH, performance_profile_interface)
         .unwrap()
+        .serve_at(DBUS_PATH, schedule_interface)
+        .unwrap()
+        .serve_at(DBUS_PATH, rule_interface)
+        .unwrap()
         .build()
         .await
         .unwrap();
 
+    tracing::debug!("Starting LED brightness signal runtime");
+    let emitter = zbus::object_server::SignalEmitter::new(&conn, DBUS_PATH).unwrap();
+    tokio_uring::spawn(LedInterface::emit_brightness_changes(
+        emitter.clone(),
+        led_handles,
+    ));
+
+    if let Some(hotplug) = led_hotplug {
+        tracing::debug!("Starting LED hotplug runtime");
+        tokio_uring::spawn(LedInterface::emit_device_changes(emitter.clone(), hotplug));
+    }
+
+    if let Some((watcher, profile_interface)) = profile_watcher {
+        tracing::debug!("Starting profile watcher runtime");
+        tokio_uring::spawn(profile_interface.watch_profiles(emitter.clone(), watcher));
+    }
+
     tracing::debug!("Starting suspend watcher runtime");
     tokio_uring::spawn(suspend::wait_for_suspend());
 
+    tracing::debug!("Starting automatic profile switching runtime");
+    tokio_uring::spawn(auto_switch_runtime.run(emitter));
+
     tracing::debug!("Starting {} led runtime(s)", led_runtimes.len());
     for runtime in led_runtimes {
         tokio_uring::spawn(runtime.run());
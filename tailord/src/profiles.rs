@@ -1,78 +1,94 @@
-use std::{collections::HashMap, path::Component, path::Path};
+use std::collections::BTreeMap;
 
-use crate::{fancontrol::profile::FanProfile, performance::PerformanceProfile};
-use tailor_api::{ColorProfile, LedControllerMode, LedDeviceInfo, LedProfile, ProfileInfo};
+use once_cell::sync::Lazy;
+
+use crate::{
+    autoswitch::ac_online,
+    config,
+    fancontrol::profile::FanProfile,
+    layers::Layers,
+    led::{LedSettings, DEFAULT_BRIGHTNESS},
+    performance::PerformanceProfile,
+    state::{self, unix_time, State, TemporaryState},
+};
+use tailor_api::{
+    match_led_profiles, ColorProfile, FanRole, LedControllerMode, LedDeviceInfo, LedMatch,
+    LedProfile, ProfileInfo, ProfileKind, RevertCondition,
+};
 use zbus::fdo;
 
-use super::util;
+use super::store;
 
 const DEFAULT_PROFILE_NAME: &str = "default";
-pub const PROFILE_DIR: &str = "/etc/tailord/profiles/";
-pub const KEYBOARD_DIR: &str = "/etc/tailord/keyboard/";
-pub const FAN_DIR: &str = "/etc/tailord/fan/";
-pub const ACTIVE_PROFILE_PATH: &str = "/etc/tailord/active_profile.json";
+pub static PROFILE_DIR: Lazy<String> = Lazy::new(|| config::get().paths.system_path("profiles/"));
+pub static KEYBOARD_DIR: Lazy<String> = Lazy::new(|| config::get().paths.system_path("keyboard/"));
+pub static FAN_DIR: Lazy<String> = Lazy::new(|| config::get().paths.system_path("fan/"));
 
 fn init_paths() {
-    [PROFILE_DIR, KEYBOARD_DIR, FAN_DIR]
-        .into_iter()
-        .for_each(|dir| {
-            std::fs::create_dir_all(dir).ok();
-        })
+    [
+        PROFILE_DIR.as_str(),
+        KEYBOARD_DIR.as_str(),
+        FAN_DIR.as_str(),
+    ]
+    .into_iter()
+    .for_each(|dir| {
+        std::fs::create_dir_all(dir).ok();
+    })
 }
 
 fn init_profiles(supported_features: SupportedFeatures) {
     fn default_profile_exists(base_path: &str) -> bool {
-        Path::new(base_path).join(DEFAULT_PROFILE_NAME).exists()
+        Layers::SYSTEM
+            .resolve(base_path, DEFAULT_PROFILE_NAME)
+            .is_ok()
     }
 
     tracing::debug!("Initialising profiles.");
-    if !default_profile_exists(KEYBOARD_DIR) {
+    if !default_profile_exists(&KEYBOARD_DIR) {
         let profile = ColorProfile::default(supported_features.mode);
-        util::write_json_sync(KEYBOARD_DIR, DEFAULT_PROFILE_NAME, &profile).ok();
+        store::write_profile_json_sync(&KEYBOARD_DIR, DEFAULT_PROFILE_NAME, &profile).ok();
     }
-    if !default_profile_exists(FAN_DIR) {
+    if !default_profile_exists(&FAN_DIR) {
         let profile = FanProfile::default();
-        util::write_json_sync(FAN_DIR, DEFAULT_PROFILE_NAME, &profile).ok();
+        store::write_profile_json_sync(&FAN_DIR, DEFAULT_PROFILE_NAME, &profile).ok();
     }
-    if !default_profile_exists(PROFILE_DIR) {
+    if !default_profile_exists(&PROFILE_DIR) {
         let profile = ProfileInfo::default();
-        util::write_json_sync(PROFILE_DIR, DEFAULT_PROFILE_NAME, &profile).ok();
+        store::write_profile_json_sync(&PROFILE_DIR, DEFAULT_PROFILE_NAME, &profile).ok();
     }
 
-    // Delete broken symlink
-    if std::fs::remove_file(ACTIVE_PROFILE_PATH).is_ok() {
-        tracing::warn!("Broken symlink at {ACTIVE_PROFILE_PATH} was removed");
+    let mut state = State::load();
+    if let Some(name) = &state.active_profile {
+        tracing::warn!("The active profile `{name}` doesn't exist anymore");
+    }
+    state.active_profile = Some(DEFAULT_PROFILE_NAME.to_owned());
+    state.active_user = None;
+    if let Err(err) = state.save_sync() {
+        tracing::error!("Failed to activate the default profile: `{err}`");
     }
-
-    // Create new symlink
-    let mut default_profile = Path::new(PROFILE_DIR).join(DEFAULT_PROFILE_NAME);
-    default_profile.set_extension("json");
-    std::os::unix::fs::symlink(default_profile, ACTIVE_PROFILE_PATH).ok();
 }
 
 fn init_profiles_if_necessary(supported_features: SupportedFeatures) {
-    if !Path::new(ACTIVE_PROFILE_PATH).exists() {
+    if !State::load().has_active_profile() {
         init_profiles(supported_features);
     }
 }
 
-fn led_profile_path(name: &str) -> fdo::Result<String> {
-    util::normalize_json_path(KEYBOARD_DIR, name)
-}
-
-fn fan_path(name: &str) -> fdo::Result<String> {
-    util::normalize_json_path(FAN_DIR, name)
+fn load_led_profile(layers: &Layers, name: &str) -> fdo::Result<ColorProfile> {
+    let (_, path) = layers.resolve(&KEYBOARD_DIR, name)?;
+    store::read_profile_json_sync(ProfileKind::Led, path)
 }
 
-fn load_led_profile(name: &str) -> fdo::Result<ColorProfile> {
-    let color_profile_data = std::fs::read(led_profile_path(name)?)
-        .map_err(|err| fdo::Error::IOError(err.to_string()))?;
-    serde_json::from_slice(&color_profile_data)
-        .map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+fn load_fan_profile(layers: &Layers, name: &str) -> fdo::Result<FanProfile> {
+    let (_, path) = layers.resolve(&FAN_DIR, name)?;
+    FanProfile::load_config(path)
 }
 
-fn load_fan_profile(name: &str) -> fdo::Result<FanProfile> {
-    FanProfile::load_config(fan_path(name)?)
+fn load_fan_profile_or_default(layers: &Layers, name: &str) -> FanProfile {
+    load_fan_profile(layers, name).unwrap_or_else(|err| {
+        tracing::error!("Failed to load fan profile called `{name}`: `{err}`");
+        FanProfile::default()
+    })
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -82,65 +98,76 @@ pub struct SupportedFeatures {
 
 #[derive(Debug)]
 pub struct Profile {
+    /// The fan profiles in the order of the fans.
     pub fans: Vec<FanProfile>,
-    pub leds: HashMap<LedDeviceInfo, ColorProfile>,
+    /// The fan profiles by the role of the fan.
+    pub fan_roles: BTreeMap<FanRole, FanProfile>,
+    /// The LED entries of the profile with their loaded settings.
+    pub leds: Vec<(LedProfile, LedSettings)>,
     pub performance_profile: Option<PerformanceProfile>,
 }
 
 impl Profile {
     pub fn init_if_necessary(supported_features: SupportedFeatures) {
         init_paths();
+        store::recover();
+        store::migrate();
+        state::migrate();
         init_profiles_if_necessary(supported_features);
     }
 
     pub fn load() -> Self {
+        let state = State::load();
+        state.remember();
         let profile_info = Self::get_active_profile_info().unwrap_or_else(|err| {
-            tracing::warn!("Failed to load active profile at `{ACTIVE_PROFILE_PATH}`: {err:?}");
+            tracing::warn!(
+                "Failed to load active profile {:?}: {err:?}",
+                state.active_profile
+            );
             ProfileInfo::default()
         });
-        tracing::info!("Loaded profile at `{ACTIVE_PROFILE_PATH}`: {profile_info:?}");
-
-        let mut led = HashMap::new();
-        for data in profile_info.leds {
-            let LedProfile {
-                device_name,
-                function,
-                profile,
-                mode,
-            } = data;
-            let info = LedDeviceInfo {
-                device_name,
-                function,
-                mode,
-            };
-            let profile = match load_led_profile(&profile) {
-                Ok(keyboard) => keyboard,
-                Err(err) => {
-                    tracing::warn!(
-                        "Failed to load keyboard color profile called `{}`: `{}`",
-                        info.device_id(),
-                        err.to_string(),
-                    );
-                    ColorProfile::default(mode)
-                }
-            };
-            led.insert(info, profile);
-        }
+        tracing::info!(
+            "Loaded profile {:?}: {profile_info:?}",
+            state.active_profile
+        );
+
+        // Resolve the referenced profiles like the user that activated the profile.
+        let layers = state.layers();
+
+        let led = profile_info
+            .leds
+            .into_iter()
+            .map(|entry| {
+                let profile = match load_led_profile(&layers, &entry.profile) {
+                    Ok(keyboard) => keyboard,
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed to load keyboard color profile called `{}`: `{}`",
+                            entry.profile,
+                            err.to_string(),
+                        );
+                        ColorProfile::default(entry.mode)
+                    }
+                };
+                let settings = LedSettings {
+                    profile,
+                    brightness: entry.brightness.unwrap_or(DEFAULT_BRIGHTNESS),
+                    gamma_correction: entry.gamma_correction,
+                    phase_offset: entry.phase_offset,
+                };
+                (entry, settings)
+            })
+            .collect();
 
         let fan = profile_info
             .fans
             .iter()
-            .map(|fan_profile| match load_fan_profile(fan_profile) {
-                Ok(fan) => fan,
-                Err(err) => {
-                    tracing::error!(
-                        "Failed to load fan color profile called `{}`: `{}`",
-                        fan_profile,
-                        err.to_string(),
-                    );
-                    FanProfile::default()
-                }
-            })
+            .map(|name| load_fan_profile_or_default(&layers, name))
+            .collect();
+        let fan_roles = profile_info
+            .fan_roles
+            .iter()
+            .map(|(role, name)| (*role, load_fan_profile_or_default(&layers, name)))
             .collect();
 
         let performance_profile = profile_info
@@ -149,44 +176,142 @@ impl Profile {
 
         Self {
             fans: fan,
+            fan_roles,
             leds: led,
             performance_profile,
         }
     }
 
-    pub async fn set_active_profile_name(name: &str) -> fdo::Result<()> {
-        std::fs::metadata(util::normalize_json_path(PROFILE_DIR, name)?)
-            .map_err(|_| fdo::Error::FileNotFound(format!("Couldn't find profile `{name}`")))?;
-
-        std::fs::remove_file(ACTIVE_PROFILE_PATH)
-            .map_err(|err| fdo::Error::IOError(err.to_string()))?;
-        std::os::unix::fs::symlink(
-            util::normalize_json_path("profiles", name)?,
-            ACTIVE_PROFILE_PATH,
-        )
-        .map_err(|err| fdo::Error::IOError(err.to_string()))
+    /// The fan profile of the fan with the given index and role.
+    /// The role takes precedence over the position.
+    pub fn fan_profile(&self, idx: usize, role: Option<FanRole>) -> FanProfile {
+        role.and_then(|role| self.fan_roles.get(&role))
+            .or_else(|| self.fans.get(idx))
+            .cloned()
+            .unwrap_or_default()
     }
 
-    pub async fn get_active_profile_name() -> fdo::Result<String> {
-        let link = std::fs::read_link(ACTIVE_PROFILE_PATH)
-            .map_err(|err| fdo::Error::IOError(err.to_string()))?;
-        let components: Vec<Component> = link.components().collect();
-        if !components.is_empty() {
-            if let Component::Normal(name) = components.last().unwrap() {
-                if let Some(name) = name.to_str() {
-                    return Ok(name.trim_end_matches(".json").to_string());
+    /// The LED settings of the detected devices.
+    ///
+    /// Entries are matched exactly by device name and function first.
+    /// Remaining devices fall back to entries with the same function
+    /// and then to entries with the same mode, so profiles survive
+    /// devices that were renamed by a kernel or driver update.
+    pub fn led_settings(&self, devices: &[LedDeviceInfo]) -> Vec<LedSettings> {
+        let entries: Vec<LedProfile> = self.leds.iter().map(|(entry, _)| entry.clone()).collect();
+
+        match_led_profiles(&entries, devices)
+            .into_iter()
+            .zip(devices)
+            .map(|(assignment, info)| match assignment {
+                Some((idx, quality)) => {
+                    if quality != LedMatch::Exact {
+                        let entry = &entries[idx];
+                        tracing::info!(
+                            "Using profile entry `{}::{}` for LED device `{}` ({quality:?} match)",
+                            entry.device_name,
+                            entry.function,
+                            info.device_id(),
+                        );
+                    }
+                    self.leds[idx].1.clone()
                 }
+                None => LedSettings::default(info.mode),
+            })
+            .collect()
+    }
+
+    /// Activate a profile on request of a user. Schedules and process
+    /// rules don't switch the profile until the lock is cleared.
+    pub async fn activate_manually(layers: &Layers, name: &str) -> fdo::Result<()> {
+        layers.resolve(&PROFILE_DIR, name)?;
+
+        State::update(|state| {
+            state.active_profile = Some(name.to_owned());
+            state.active_user = layers.user();
+            state.automatic = None;
+            state.temporary = None;
+            state.locked = true;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Activate a profile until the condition is met. Afterwards, the
+    /// previous profile is activated again. Schedules and process rules
+    /// don't switch the profile while it's active.
+    pub async fn activate_temporarily(
+        layers: &Layers,
+        name: &str,
+        until: RevertCondition,
+    ) -> fdo::Result<()> {
+        layers.resolve(&PROFILE_DIR, name)?;
+        let deadline = match until {
+            RevertCondition::After(0) => {
+                return Err(fdo::Error::InvalidArgs(
+                    "The duration must be at least one second".to_string(),
+                ))
             }
-        }
+            RevertCondition::After(secs) => {
+                Some(unix_time().checked_add(secs).ok_or_else(|| {
+                    fdo::Error::InvalidArgs(format!("The duration of {secs}s is too long"))
+                })?)
+            }
+            _ => None,
+        };
+        let ac_online = match until {
+            RevertCondition::AcChange => Some(
+                ac_online()
+                    .ok_or_else(|| fdo::Error::NotSupported("No AC adapter found".to_string()))?,
+            ),
+            _ => None,
+        };
 
-        Err(fdo::Error::InvalidFileContent(
-            "The active profile isn't set correctly".to_string(),
-        ))
+        State::update(|state| {
+            // Keep the profile from before the first temporary profile.
+            let (previous_profile, previous_user) = match state.temporary.take() {
+                Some(temporary) => (temporary.previous_profile, temporary.previous_user),
+                None => (state.active_profile.clone(), state.active_user),
+            };
+            state.temporary = Some(TemporaryState {
+                profile: name.to_owned(),
+                until,
+                deadline,
+                ac_online,
+                previous_profile,
+                previous_user,
+            });
+            state.active_profile = Some(name.to_owned());
+            state.active_user = layers.user();
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_active_profile_name() -> fdo::Result<String> {
+        State::load().active_profile.ok_or_else(|| {
+            fdo::Error::InvalidFileContent("The active profile isn't set".to_string())
+        })
     }
 
     pub fn get_active_profile_info() -> fdo::Result<ProfileInfo> {
-        let data = std::fs::read(ACTIVE_PROFILE_PATH)
-            .map_err(|err| fdo::Error::IOError(err.to_string()))?;
-        serde_json::from_slice(&data).map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+        let state = State::load();
+        let name = state.active_profile.as_deref().ok_or_else(|| {
+            fdo::Error::InvalidFileContent("The active profile isn't set".to_string())
+        })?;
+        store::read_resolved_profile(&state.layers(), name)
+    }
+
+    /// The names of the active profile and the profiles it inherits from.
+    /// Broken chains are cut off at the first profile that can't be read.
+    pub fn get_active_profile_chain() -> Vec<String> {
+        let state = State::load();
+        let Some(name) = state.active_profile.clone() else {
+            return Vec::new();
+        };
+        match store::inheritance_chain(&state.layers(), &name) {
+            Ok(chain) => chain.into_iter().map(|(name, _)| name).collect(),
+            Err(_) => vec![name],
+        }
     }
 }
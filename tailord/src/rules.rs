@@ -0,0 +1,119 @@
+use std::cmp::Reverse;
+
+use once_cell::sync::Lazy;
+use tailor_api::ProcessRule;
+use zbus::fdo;
+
+use crate::{config, layers::Layers, profiles::PROFILE_DIR, util};
+
+pub static RULE_DIR: Lazy<String> = Lazy::new(|| config::get().paths.system_path("rules/"));
+
+/// Read all process rules. Invalid files are skipped.
+pub async fn load_rules() -> Vec<(String, ProcessRule)> {
+    let names = util::get_profiles(&RULE_DIR).await.unwrap_or_default();
+    let mut rules = Vec::new();
+    for name in names {
+        match read_rule(&name).await {
+            Ok(rule) => rules.push((name, rule)),
+            Err(err) => tracing::warn!("Failed to load rule `{name}`: `{err}`"),
+        }
+    }
+    rules
+}
+
+pub async fn read_rule(name: &str) -> fdo::Result<ProcessRule> {
+    let data = util::read_file(&RULE_DIR, name).await?;
+    serde_json::from_str(&data).map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+}
+
+/// Validate a process rule and store it.
+pub async fn write_rule(name: &str, value: &str) -> fdo::Result<()> {
+    let rule: ProcessRule =
+        serde_json::from_str(value).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+    if rule.command.trim().is_empty() {
+        return Err(fdo::Error::InvalidArgs("The command is empty".to_owned()));
+    }
+    Layers::SYSTEM.resolve(&PROFILE_DIR, &rule.profile)?;
+
+    std::fs::create_dir_all(RULE_DIR.as_str())
+        .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+    let data = serde_json::to_vec_pretty(&rule).unwrap();
+    util::write_file(&RULE_DIR, name, &data).await
+}
+
+/// The command lines of all running processes.
+/// Kernel threads and processes that exited in the meantime are left out.
+pub fn running_processes() -> Vec<Vec<String>> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_str().is_some_and(is_pid))
+        .filter_map(|entry| std::fs::read(entry.path().join("cmdline")).ok())
+        .map(|cmdline| parse_cmdline(&cmdline))
+        .filter(|args| !args.is_empty())
+        .collect()
+}
+
+fn is_pid(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// The arguments are separated by null bytes.
+fn parse_cmdline(cmdline: &[u8]) -> Vec<String> {
+    cmdline
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+/// The matching rule with the highest priority.
+pub fn current_rule<'a>(
+    rules: &'a [(String, ProcessRule)],
+    processes: &[Vec<String>],
+) -> Option<&'a (String, ProcessRule)> {
+    rules
+        .iter()
+        .filter(|(_, rule)| processes.iter().any(|args| rule.matches(args)))
+        // Prefer the first name if the priorities are equal.
+        .min_by_key(|(_, rule)| Reverse(rule.priority))
+}
+
+#[cfg(test)]
+mod test {
+    use tailor_api::ProcessRule;
+
+    use super::{current_rule, parse_cmdline};
+
+    #[test]
+    fn highest_priority_wins() {
+        let rule = |command: &str, priority| ProcessRule {
+            profile: "performance".to_owned(),
+            command: command.to_owned(),
+            priority,
+        };
+        let rules = [
+            ("blender".to_owned(), rule("blender", 0)),
+            ("compile".to_owned(), rule("cargo build", 10)),
+            ("steam".to_owned(), rule("steam", 0)),
+        ];
+
+        let processes = vec![
+            parse_cmdline(b"/usr/bin/steam\0-silent\0"),
+            parse_cmdline(b"/usr/bin/blender\0"),
+        ];
+        let current = current_rule(&rules, &processes).map(|(name, _)| name.as_str());
+        assert_eq!(current, Some("blender"));
+
+        let processes = vec![
+            parse_cmdline(b"/usr/bin/steam\0"),
+            parse_cmdline(b"cargo\0build\0--release\0"),
+        ];
+        let current = current_rule(&rules, &processes).map(|(name, _)| name.as_str());
+        assert_eq!(current, Some("compile"));
+
+        assert_eq!(current_rule(&rules, &[]), None);
+    }
+}
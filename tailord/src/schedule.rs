@@ -0,0 +1,103 @@
+use once_cell::sync::Lazy;
+use tailor_api::{ProfileSchedule, TimeOfDay, Weekday};
+use zbus::fdo;
+
+use crate::{config, layers::Layers, profiles::PROFILE_DIR, util};
+
+pub static SCHEDULE_DIR: Lazy<String> = Lazy::new(|| config::get().paths.system_path("schedules/"));
+
+/// Read all schedules. Invalid files are skipped.
+pub async fn load_schedules() -> Vec<(String, ProfileSchedule)> {
+    let names = util::get_profiles(&SCHEDULE_DIR).await.unwrap_or_default();
+    let mut schedules = Vec::new();
+    for name in names {
+        match read_schedule(&name).await {
+            Ok(schedule) => schedules.push((name, schedule)),
+            Err(err) => tracing::warn!("Failed to load schedule `{name}`: `{err}`"),
+        }
+    }
+    schedules
+}
+
+pub async fn read_schedule(name: &str) -> fdo::Result<ProfileSchedule> {
+    let data = util::read_file(&SCHEDULE_DIR, name).await?;
+    serde_json::from_str(&data).map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+}
+
+/// Validate a schedule and store it.
+pub async fn write_schedule(name: &str, value: &str) -> fdo::Result<()> {
+    let schedule: ProfileSchedule =
+        serde_json::from_str(value).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+    Layers::SYSTEM.resolve(&PROFILE_DIR, &schedule.profile)?;
+
+    std::fs::create_dir_all(SCHEDULE_DIR.as_str())
+        .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+    let data = serde_json::to_vec_pretty(&schedule).unwrap();
+    util::write_file(&SCHEDULE_DIR, name, &data).await
+}
+
+extern "C" {
+    /// Not exported by the libc crate.
+    fn tzset();
+}
+
+/// The current day and time in the local time zone
+/// and the seconds until the next minute starts.
+pub fn local_time() -> (Weekday, TimeOfDay, u64) {
+    // SAFETY: `localtime_r` only writes to the provided struct.
+    let tm = unsafe {
+        // Pick up changes of the time zone.
+        tzset();
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        tm
+    };
+    let day = Weekday::from_days_since_sunday(tm.tm_wday as u32);
+    let time = TimeOfDay::new(tm.tm_hour as u8, tm.tm_min as u8).unwrap_or_default();
+    // Leap seconds have the value 60.
+    let remaining = 60u64.saturating_sub(tm.tm_sec as u64).max(1);
+    (day, time, remaining)
+}
+
+/// The schedule that started last among the active schedules.
+pub fn current_schedule(
+    schedules: &[(String, ProfileSchedule)],
+    day: Weekday,
+    time: TimeOfDay,
+) -> Option<&(String, ProfileSchedule)> {
+    schedules
+        .iter()
+        .filter_map(|entry| Some((entry.1.active_since(day, time)?, entry)))
+        .min_by_key(|(active_since, _)| *active_since)
+        .map(|(_, entry)| entry)
+}
+
+#[cfg(test)]
+mod test {
+    use tailor_api::{ProfileSchedule, Weekday};
+
+    use super::current_schedule;
+
+    #[test]
+    fn latest_schedule_wins() {
+        let schedule = |profile: &str, start: &str, end: &str| ProfileSchedule {
+            profile: profile.to_owned(),
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            days: Vec::new(),
+        };
+        let schedules = [
+            ("evening".to_owned(), schedule("quiet", "18:00", "23:00")),
+            ("night".to_owned(), schedule("off", "22:00", "07:00")),
+        ];
+
+        let current = |time: &str| {
+            current_schedule(&schedules, Weekday::Monday, time.parse().unwrap())
+                .map(|(name, _)| name.as_str())
+        };
+        assert_eq!(current("19:00"), Some("evening"));
+        assert_eq!(current("22:30"), Some("night"));
+        assert_eq!(current("12:00"), None);
+    }
+}
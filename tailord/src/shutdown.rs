@@ -1,13 +1,15 @@
 use futures::StreamExt;
-use signal_hook::consts::{SIGINT, SIGQUIT, SIGTERM};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
 use signal_hook_tokio::Signals;
 use tokio::sync::broadcast;
 
+use crate::config;
+
 pub fn setup() -> broadcast::Receiver<()> {
     // Setup shutdown
     let (shutdown_sender, shutdown_receiver) = broadcast::channel(1);
 
-    let signals = Signals::new([SIGTERM, SIGINT, SIGQUIT]).unwrap();
+    let signals = Signals::new([SIGTERM, SIGINT, SIGQUIT, SIGHUP]).unwrap();
     tracing::debug!("Starting signal handler runtime");
     tokio_uring::spawn(handle_signals(signals, shutdown_sender));
 
@@ -27,6 +29,11 @@ async fn handle_signals(mut signals: Signals, shutdown_sender: broadcast::Sender
                 tracing::info!("Received a shutdown signal");
                 shutdown_sender.send(()).unwrap();
             }
+            SIGHUP => {
+                if let Err(err) = config::reload() {
+                    tracing::error!("Keeping the previous configuration: {err}");
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -0,0 +1,360 @@
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tailor_api::{RevertCondition, TemporaryProfile};
+use zbus::fdo;
+
+use crate::{config, layers::Layers, profiles::PROFILE_DIR, util};
+
+/// The state of the daemon that changes at runtime.
+pub static STATE_PATH: Lazy<String> = Lazy::new(|| config::get().paths.state_path("state.json"));
+
+/// The symlink to the active profile that was used by older versions.
+static LEGACY_ACTIVE_PROFILE_PATH: Lazy<String> =
+    Lazy::new(|| config::get().paths.system_path("active_profile.json"));
+
+/// Serializes the changes of the state, so tasks that change
+/// it at the same time don't overwrite each other's changes.
+static UPDATE_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// The state that was last written, applied or read by the watcher.
+static KNOWN_STATE: Lazy<Mutex<Option<State>>> = Lazy::new(|| Mutex::new(None));
+
+/// State that is kept across restarts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// The name of the active global profile.
+    pub active_profile: Option<String>,
+    /// The user that activated the profile. The profiles
+    /// are resolved in the layers of this user.
+    pub active_user: Option<u32>,
+    /// The schedule or rule that activated the active profile.
+    pub automatic: Option<AutomaticState>,
+    /// The profile was activated manually and
+    /// schedules and rules are paused until the lock is cleared.
+    pub locked: bool,
+    /// The active profile was activated temporarily.
+    pub temporary: Option<TemporaryState>,
+}
+
+/// What activated a profile automatically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trigger {
+    Schedule(String),
+    Rule(String),
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trigger::Schedule(name) => write!(f, "Schedule `{name}`"),
+            Trigger::Rule(name) => write!(f, "Rule `{name}`"),
+        }
+    }
+}
+
+/// Remembers the profile that was active before a schedule or rule
+/// took over, so it can be activated again when it ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutomaticState {
+    pub trigger: Trigger,
+    /// The profile that was activated by the trigger.
+    pub profile: String,
+    pub previous_profile: Option<String>,
+    pub previous_user: Option<u32>,
+}
+
+/// A profile that was activated until a condition is met.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemporaryState {
+    pub profile: String,
+    pub until: RevertCondition,
+    /// The UNIX time at which a profile with [`RevertCondition::After`] ends.
+    /// The wall clock is used, so the time in suspend counts as well.
+    pub deadline: Option<u64>,
+    /// Whether the AC adapter was plugged in when the profile was activated.
+    pub ac_online: Option<bool>,
+    pub previous_profile: Option<String>,
+    pub previous_user: Option<u32>,
+}
+
+impl TemporaryState {
+    pub fn info(&self) -> TemporaryProfile {
+        TemporaryProfile {
+            name: self.profile.clone(),
+            until: self.until,
+            remaining: self
+                .deadline
+                .map(|deadline| deadline.saturating_sub(unix_time())),
+        }
+    }
+}
+
+/// The seconds since the UNIX epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl State {
+    /// Read the state. A missing or broken file is the default state.
+    pub fn load() -> Self {
+        match std::fs::read_to_string(STATE_PATH.as_str()) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|err| {
+                tracing::warn!("Ignoring invalid state at `{}`: `{err}`", *STATE_PATH);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Load the state, change it with `f` and save it again, while
+    /// no other task can change it. Nothing is saved if `f` fails
+    /// or doesn't change the state.
+    pub async fn update<T>(f: impl FnOnce(&mut Self) -> fdo::Result<T>) -> fdo::Result<T> {
+        let _lock = UPDATE_LOCK.lock().await;
+        let previous = Self::load();
+        let mut state = previous.clone();
+        let result = f(&mut state)?;
+        if state != previous {
+            state.save().await?;
+        }
+        Ok(result)
+    }
+
+    /// Replace the state file atomically.
+    async fn save(&self) -> fdo::Result<()> {
+        let data = serde_json::to_vec_pretty(self).unwrap();
+        create_state_dir()?;
+        self.remember();
+        util::write_atomic(Path::new(STATE_PATH.as_str()), &data)
+            .await
+            .map_err(|err| fdo::Error::IOError(err.to_string()))
+    }
+
+    /// Like [`State::save`] for code that isn't async.
+    pub fn save_sync(&self) -> fdo::Result<()> {
+        let data = serde_json::to_vec_pretty(self).unwrap();
+        create_state_dir()?;
+        self.remember();
+        let path = Path::new(STATE_PATH.as_str());
+        let temp = util::temp_path(path);
+        std::fs::write(&temp, data)
+            .and_then(|_| std::fs::rename(&temp, path))
+            .map_err(|err| fdo::Error::IOError(err.to_string()))
+    }
+
+    /// Read the state after the file was changed on disk.
+    /// Returns whether another profile was activated than in the
+    /// known state, so writes of the daemon itself are ignored.
+    pub fn reload_changed() -> bool {
+        let state = Self::load();
+        let mut known = KNOWN_STATE.lock().unwrap();
+        let changed = known.as_ref().map_or(true, |known| {
+            known.active_profile != state.active_profile || known.active_user != state.active_user
+        });
+        *known = Some(state);
+        changed
+    }
+
+    /// Remember the state as known, so the watcher doesn't reload it again.
+    pub fn remember(&self) {
+        *KNOWN_STATE.lock().unwrap() = Some(self.clone());
+    }
+
+    /// Whether the active profile is set and exists.
+    pub fn has_active_profile(&self) -> bool {
+        self.active_profile
+            .as_ref()
+            .is_some_and(|name| self.layers().resolve(&PROFILE_DIR, name).is_ok())
+    }
+
+    /// Activate the previous profile again after `current` ends, unless
+    /// another profile was activated manually in the meantime.
+    pub fn restore_previous(
+        &mut self,
+        current: &str,
+        previous_profile: Option<String>,
+        previous_user: Option<u32>,
+    ) {
+        let Some(previous) = previous_profile else {
+            return;
+        };
+        if self.active_profile.as_deref() != Some(current) {
+            return;
+        }
+
+        let layers = previous_user
+            .map(Layers::for_user)
+            .unwrap_or(Layers::SYSTEM);
+        if layers.resolve(&PROFILE_DIR, &previous).is_ok() {
+            self.active_profile = Some(previous);
+            self.active_user = previous_user;
+        } else {
+            tracing::warn!("The previous profile `{previous}` doesn't exist anymore");
+        }
+    }
+
+    /// End the temporary profile and activate the previous profile again.
+    /// Returns the name of the temporary profile.
+    pub fn end_temporary(&mut self) -> Option<String> {
+        let temporary = self.temporary.take()?;
+        tracing::info!("Temporary profile `{}` ended", temporary.profile);
+        self.restore_previous(
+            &temporary.profile,
+            temporary.previous_profile,
+            temporary.previous_user,
+        );
+        Some(temporary.profile)
+    }
+
+    /// Follow the rename of the profile `from` to `to` in `layers`
+    /// everywhere the state refers to it. The users that activated the
+    /// profiles are kept. Returns whether the active profile was renamed.
+    pub fn rename_profile(&mut self, layers: &Layers, from: &str, to: &str) -> bool {
+        let rename = |name: &mut String, user: Option<u32>| {
+            if name == from && refers_to_renamed(layers, from, user) {
+                *name = to.to_owned();
+            }
+        };
+        let rename_optional = |name: &mut Option<String>, user: Option<u32>| {
+            if let Some(name) = name {
+                rename(name, user);
+            }
+        };
+
+        let active_user = self.active_user;
+        let previous_active = self.active_profile.clone();
+        rename_optional(&mut self.active_profile, active_user);
+        if let Some(temporary) = &mut self.temporary {
+            rename(&mut temporary.profile, active_user);
+            rename_optional(&mut temporary.previous_profile, temporary.previous_user);
+        }
+        if let Some(automatic) = &mut self.automatic {
+            // Schedules and rules resolve their profiles in the system layer.
+            rename(&mut automatic.profile, None);
+            rename_optional(&mut automatic.previous_profile, automatic.previous_user);
+        }
+        self.active_profile != previous_active
+    }
+
+    /// The layers that the active profile is resolved in.
+    pub fn layers(&self) -> Layers {
+        self.active_user
+            .map(Layers::for_user)
+            .unwrap_or(Layers::SYSTEM)
+    }
+}
+
+/// Whether `from`, resolved for `user` before it was renamed in `layers`,
+/// was the renamed profile. Users with their own copy of a renamed
+/// system profile still resolve `from` and keep referring to their copy.
+fn refers_to_renamed(layers: &Layers, from: &str, user: Option<u32>) -> bool {
+    match (layers.user(), user) {
+        (Some(_), _) => user == layers.user(),
+        (None, None) => true,
+        (None, Some(user)) => Layers::for_user(user).resolve(&PROFILE_DIR, from).is_err(),
+    }
+}
+
+fn create_state_dir() -> fdo::Result<()> {
+    std::fs::create_dir_all(config::get().paths.state)
+        .map_err(|err| fdo::Error::IOError(err.to_string()))
+}
+
+/// Move the active profile from the symlink of older versions to the state file.
+pub fn migrate() {
+    let Ok(target) = std::fs::read_link(LEGACY_ACTIVE_PROFILE_PATH.as_str()) else {
+        return;
+    };
+
+    if State::load().active_profile.is_none() {
+        let name = target
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .map(ToOwned::to_owned);
+        let state = State {
+            active_profile: name,
+            active_user: Layers::for_path(&target).user(),
+            ..Default::default()
+        };
+        match state.save_sync() {
+            Ok(()) => tracing::info!("Migrated the active profile to `{}`", *STATE_PATH),
+            Err(err) => {
+                tracing::warn!("Failed to migrate the active profile: `{err}`");
+                return;
+            }
+        }
+    }
+
+    // /etc might be read-only, the symlink is ignored in this case.
+    if let Err(err) = std::fs::remove_file(LEGACY_ACTIVE_PROFILE_PATH.as_str()) {
+        tracing::debug!("Failed to remove the legacy active profile symlink: `{err}`");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tailor_api::RevertCondition;
+
+    use super::{AutomaticState, State, TemporaryState, Trigger};
+    use crate::layers::Layers;
+
+    #[test]
+    fn parse_state() {
+        assert_eq!(
+            serde_json::from_str::<State>("{}").unwrap(),
+            State::default()
+        );
+
+        let state: State = serde_json::from_str(r#"{ "active_profile": "silent" }"#).unwrap();
+        assert_eq!(state.active_profile.as_deref(), Some("silent"));
+        assert_eq!(state.active_user, None);
+    }
+
+    #[test]
+    fn rename_profile() {
+        let mut state = State {
+            active_profile: Some("silent".to_string()),
+            temporary: Some(TemporaryState {
+                profile: "silent".to_string(),
+                until: RevertCondition::AcChange,
+                deadline: None,
+                ac_online: Some(false),
+                previous_profile: Some("office".to_string()),
+                previous_user: None,
+            }),
+            automatic: Some(AutomaticState {
+                trigger: Trigger::Schedule("night".to_string()),
+                profile: "office".to_string(),
+                previous_profile: Some("silent".to_string()),
+                previous_user: None,
+            }),
+            ..Default::default()
+        };
+
+        assert!(state.rename_profile(&Layers::SYSTEM, "silent", "quiet"));
+        assert_eq!(state.active_profile.as_deref(), Some("quiet"));
+        let temporary = state.temporary.as_ref().unwrap();
+        assert_eq!(temporary.profile, "quiet");
+        assert_eq!(temporary.previous_profile.as_deref(), Some("office"));
+        let automatic = state.automatic.as_ref().unwrap();
+        assert_eq!(automatic.profile, "office");
+        assert_eq!(automatic.previous_profile.as_deref(), Some("quiet"));
+
+        assert!(!state.rename_profile(&Layers::SYSTEM, "office", "work"));
+        assert_eq!(state.automatic.unwrap().profile, "work");
+        assert_eq!(
+            state.temporary.unwrap().previous_profile.as_deref(),
+            Some("work")
+        );
+    }
+}
@@ -0,0 +1,753 @@
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tailor_api::{
+    to_versioned_json, upgrade_profile, ColorProfile, ProfileDefinition, ProfileInfo, ProfileKind,
+    ProfileOrigin, RemovalStrategy, UpgradedProfile,
+};
+use tokio::sync::Mutex;
+use zbus::{fdo, DBusError};
+
+use crate::{
+    config,
+    layers::{self, Layers},
+    led,
+    profiles::{Profile, FAN_DIR, KEYBOARD_DIR, PROFILE_DIR},
+    rules::{self, RULE_DIR},
+    schedule::{self, SCHEDULE_DIR},
+    util,
+};
+
+/// Lists the steps of the transaction that is currently committed,
+/// so they can be completed after a crash.
+static JOURNAL_PATH: Lazy<String> =
+    Lazy::new(|| config::get().paths.system_path("transaction.journal"));
+
+/// Copies of profile files from before a schema upgrade.
+static BACKUP_DIR: Lazy<String> = Lazy::new(|| config::get().paths.system_path("backup/"));
+
+/// Only one transaction can be committed at a time.
+static COMMIT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Errors of the profile store.
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "com.tux.Tailor.Error")]
+pub enum StoreError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// The profile is still referenced by other profiles.
+    /// The message lists the dependents.
+    ProfileInUse(String),
+}
+
+impl From<fdo::Error> for StoreError {
+    fn from(err: fdo::Error) -> Self {
+        Self::ZBus(err.into())
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+enum Operation {
+    /// Move a staged temporary file to its final path.
+    Write {
+        temp: PathBuf,
+        target: PathBuf,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Remove {
+        path: PathBuf,
+    },
+}
+
+impl Operation {
+    /// Apply the operation. While `recovering`, operations that
+    /// were already applied before a crash are skipped.
+    /// Otherwise, a missing file is an error.
+    fn apply(&self, recovering: bool) -> std::io::Result<()> {
+        let source = match self {
+            Operation::Write { temp, .. } => temp,
+            Operation::Rename { from, .. } => from,
+            Operation::Remove { path } => path,
+        };
+        if recovering && !source.exists() {
+            return Ok(());
+        }
+
+        match self {
+            Operation::Write { temp, target } => std::fs::rename(temp, target),
+            Operation::Rename { from, to } => std::fs::rename(from, to),
+            Operation::Remove { path } => std::fs::remove_file(path),
+        }
+    }
+}
+
+/// Changes to multiple profile files that are applied together.
+///
+/// New file contents are staged in temporary files first. On commit,
+/// the steps are recorded in a journal before they are applied, so
+/// [`recover`] can finish them if the daemon crashes midway.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    operations: Vec<Operation>,
+}
+
+impl Transaction {
+    pub async fn write_json<T: Serialize>(
+        &mut self,
+        base_path: &str,
+        name: &str,
+        data: &T,
+    ) -> fdo::Result<()> {
+        let data = to_versioned_json(data).map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        self.write(base_path, name, data.as_bytes()).await
+    }
+
+    /// Stage a file that isn't a profile and has no schema version.
+    pub async fn write(&mut self, base_path: &str, name: &str, data: &[u8]) -> fdo::Result<()> {
+        let target = PathBuf::from(util::normalize_json_path(base_path, name)?);
+        let temp = util::write_temp_file(&target, data)
+            .await
+            .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+        self.operations.push(Operation::Write { temp, target });
+        Ok(())
+    }
+
+    pub fn rename(&mut self, base_path: &str, from: &str, to: &str) -> fdo::Result<()> {
+        self.operations.push(Operation::Rename {
+            from: util::normalize_json_path(base_path, from)?.into(),
+            to: util::normalize_json_path(base_path, to)?.into(),
+        });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, base_path: &str, name: &str) -> fdo::Result<()> {
+        self.operations.push(Operation::Remove {
+            path: util::normalize_json_path(base_path, name)?.into(),
+        });
+        Ok(())
+    }
+
+    pub async fn commit(mut self) -> fdo::Result<()> {
+        let _lock = COMMIT_LOCK.lock().await;
+        let operations = std::mem::take(&mut self.operations);
+
+        let journal = serde_json::to_vec(&operations).unwrap();
+        util::write_atomic(Path::new(JOURNAL_PATH.as_str()), &journal)
+            .await
+            .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+
+        for operation in &operations {
+            if let Err(err) = operation.apply(false) {
+                // Keep the journal, the remaining steps are applied on the next start.
+                tracing::error!("Failed to apply {operation:?}: `{err}`");
+                return Err(fdo::Error::IOError(err.to_string()));
+            }
+        }
+
+        tokio::fs::remove_file(JOURNAL_PATH.as_str())
+            .await
+            .map_err(|err| fdo::Error::IOError(err.to_string()))
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // Remove the staged files of transactions that weren't committed.
+        for operation in &self.operations {
+            if let Operation::Write { temp, .. } = operation {
+                std::fs::remove_file(temp).ok();
+            }
+        }
+    }
+}
+
+/// The kind of the profiles in a directory of any layer.
+fn profile_kind(base_path: &str) -> ProfileKind {
+    let dir_name = Path::new(base_path).file_name().unwrap_or_default();
+    match dir_name.to_str() {
+        Some("profiles") => ProfileKind::Global,
+        Some("keyboard") => ProfileKind::Led,
+        Some("fan") => ProfileKind::Fan,
+        _ => unreachable!("Unknown profile directory `{base_path}`"),
+    }
+}
+
+fn upgrade(kind: ProfileKind, data: &str) -> fdo::Result<UpgradedProfile> {
+    upgrade_profile(kind, data).map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+}
+
+/// Read a profile from the highest layer that contains it
+/// and upgrade it to the latest schema.
+/// Returns the plain profile without the version.
+pub async fn read_profile(layers: &Layers, base_path: &str, name: &str) -> fdo::Result<String> {
+    let (_, path) = layers.resolve(base_path, name)?;
+    let data = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+    let upgraded = upgrade(profile_kind(base_path), &data)?;
+    Ok(upgraded.profile.to_string())
+}
+
+pub async fn read_profile_json<T: DeserializeOwned>(
+    layers: &Layers,
+    base_path: &str,
+    name: &str,
+) -> fdo::Result<T> {
+    let (_, path) = layers.resolve(base_path, name)?;
+    let data = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+    let upgraded = upgrade(profile_kind(base_path), &data)?;
+    serde_json::from_value(upgraded.profile)
+        .map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+}
+
+/// Like [`read_profile_json`] for the path of a profile file.
+pub fn read_profile_json_sync<T: DeserializeOwned>(
+    kind: ProfileKind,
+    path: impl AsRef<Path>,
+) -> fdo::Result<T> {
+    let data = std::fs::read_to_string(path).map_err(|err| fdo::Error::IOError(err.to_string()))?;
+    serde_json::from_value(upgrade(kind, &data)?.profile)
+        .map_err(|err| fdo::Error::InvalidFileContent(err.to_string()))
+}
+
+/// A global profile followed by the profiles it inherits from.
+pub fn inheritance_chain(
+    layers: &Layers,
+    name: &str,
+) -> fdo::Result<Vec<(String, ProfileDefinition)>> {
+    let mut chain: Vec<(String, ProfileDefinition)> = Vec::new();
+    let mut next = Some(name.to_owned());
+    while let Some(name) = next {
+        if chain.iter().any(|(existing, _)| *existing == name) {
+            return Err(fdo::Error::InvalidFileContent(format!(
+                "Profile `{name}` inherits from itself"
+            )));
+        }
+        let (_, path) = layers.resolve(&PROFILE_DIR, &name)?;
+        let definition: ProfileDefinition = read_profile_json_sync(ProfileKind::Global, path)?;
+        next = definition.base.clone();
+        chain.push((name, definition));
+    }
+    Ok(chain)
+}
+
+/// Read a global profile together with the fields it inherits from its base profiles.
+pub fn read_resolved_profile(layers: &Layers, name: &str) -> fdo::Result<ProfileInfo> {
+    let chain = inheritance_chain(layers, name)?;
+    Ok(chain
+        .into_iter()
+        .rev()
+        .fold(ProfileInfo::default(), |base, (_, definition)| {
+            definition.resolve(base)
+        }))
+}
+
+/// Fail if the base profile doesn't exist or inherits from
+/// the profile itself, which would create a cycle.
+fn ensure_valid_base(layers: &Layers, name: &str, base: &str) -> fdo::Result<()> {
+    let chain = inheritance_chain(layers, base)
+        .map_err(|err| fdo::Error::InvalidArgs(format!("Invalid base profile `{base}`: {err}")))?;
+    if chain.iter().any(|(ancestor, _)| ancestor == name) {
+        Err(fdo::Error::InvalidArgs(format!(
+            "Profile `{name}` can't inherit from `{base}`, which inherits from `{name}`"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Validate a plain profile and store it with the current
+/// schema version in the writable layer.
+pub async fn write_profile(
+    layers: &Layers,
+    base_path: &str,
+    name: &str,
+    data: &str,
+) -> fdo::Result<()> {
+    let kind = profile_kind(base_path);
+    let upgraded =
+        upgrade_profile(kind, data).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+    if let (ProfileKind::Global, Some(base)) =
+        (kind, upgraded.profile.get("base").and_then(Value::as_str))
+    {
+        ensure_valid_base(layers, name, base)?;
+    }
+    if kind == ProfileKind::Led {
+        let profile: ColorProfile = serde_json::from_value(upgraded.profile.clone())
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        led::check_sorted_points(&profile).map_err(fdo::Error::InvalidArgs)?;
+    }
+    write_profile_json(&layers.writable_dir(base_path)?, name, &upgraded.profile).await
+}
+
+pub async fn write_profile_json<T: Serialize>(
+    base_path: &str,
+    name: &str,
+    profile: &T,
+) -> fdo::Result<()> {
+    let data = to_versioned_json(profile).map_err(|err| fdo::Error::Failed(err.to_string()))?;
+    util::write_file(base_path, name, data.as_bytes()).await
+}
+
+pub fn write_profile_json_sync<T: Serialize>(
+    base_path: &str,
+    name: &str,
+    profile: &T,
+) -> fdo::Result<()> {
+    let data = to_versioned_json(profile).map_err(|err| fdo::Error::Failed(err.to_string()))?;
+    util::write_file_sync(base_path, name, data.as_bytes())
+}
+
+/// Upgrade all profile files to the latest schema.
+/// The original files are copied to the backup directory first.
+pub fn migrate() {
+    for base_path in [
+        PROFILE_DIR.as_str(),
+        KEYBOARD_DIR.as_str(),
+        FAN_DIR.as_str(),
+    ]
+    .into_iter()
+    .flat_map(layers::writable_dirs)
+    {
+        let base_path = base_path.as_str();
+        let Ok(entries) = std::fs::read_dir(base_path) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(name) = file_name.strip_suffix(".json") else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let Ok(data) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            match upgrade_profile(profile_kind(base_path), &data) {
+                Ok(upgraded) if upgraded.was_upgraded() => {
+                    if let Err(err) = backup_and_write(base_path, name, &data, &upgraded) {
+                        tracing::error!("Failed to upgrade profile `{}`: `{err}`", path.display());
+                    } else {
+                        tracing::info!(
+                            "Upgraded profile `{}` from schema version {}",
+                            path.display(),
+                            upgraded.from_version
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!("Invalid profile `{}`: `{err}`", path.display()),
+            }
+        }
+    }
+}
+
+fn backup_and_write(
+    base_path: &str,
+    name: &str,
+    original: &str,
+    upgraded: &UpgradedProfile,
+) -> fdo::Result<()> {
+    let backup_dir = Path::new(BACKUP_DIR.as_str()).join(layers::relative_dir(base_path));
+    std::fs::create_dir_all(&backup_dir)
+        .and_then(|_| {
+            std::fs::write(
+                backup_dir.join(format!("{name}.v{}.json", upgraded.from_version)),
+                original,
+            )
+        })
+        .map_err(|err| fdo::Error::IOError(err.to_string()))?;
+
+    write_profile_json_sync(base_path, name, &upgraded.profile)
+}
+
+/// Finish a transaction that was interrupted by a crash
+/// and remove temporary files of unfinished writes.
+pub fn recover() {
+    if let Ok(journal) = std::fs::read(JOURNAL_PATH.as_str()) {
+        tracing::warn!("Completing an interrupted profile transaction");
+        match serde_json::from_slice::<Vec<Operation>>(&journal) {
+            Ok(operations) => {
+                for operation in operations {
+                    if let Err(err) = operation.apply(true) {
+                        tracing::error!("Failed to apply {operation:?}: `{err}`");
+                    }
+                }
+            }
+            Err(err) => tracing::error!("Invalid transaction journal: `{err}`"),
+        }
+        std::fs::remove_file(JOURNAL_PATH.as_str()).ok();
+    }
+
+    for dir in [
+        PROFILE_DIR.as_str(),
+        KEYBOARD_DIR.as_str(),
+        FAN_DIR.as_str(),
+    ]
+    .into_iter()
+    .flat_map(layers::writable_dirs)
+    {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if file_name.starts_with('.') && file_name.ends_with(".tmp") {
+                tracing::info!("Removing unfinished write `{file_name}`");
+                std::fs::remove_file(entry.path()).ok();
+            }
+        }
+    }
+}
+
+/// The names of the visible global profiles that reference a profile.
+/// Only the fields that are set in each profile are checked.
+pub async fn dependents(
+    layers: &Layers,
+    references: impl Fn(&ProfileDefinition) -> bool,
+) -> fdo::Result<Vec<String>> {
+    let mut dependents = Vec::new();
+    for profile in layers.names(&PROFILE_DIR).await? {
+        match read_profile_json::<ProfileDefinition>(layers, &PROFILE_DIR, &profile).await {
+            Ok(info) => {
+                if references(&info) {
+                    dependents.push(profile);
+                }
+            }
+            Err(err) => tracing::warn!("Failed to read profile `{profile}`: `{err}`"),
+        }
+    }
+    Ok(dependents)
+}
+
+/// Fail with [`StoreError::ProfileInUse`] if a global profile in the layer
+/// of a user references a profile of the system layer that is changed.
+///
+/// The layers of other users can't be changed together with the system
+/// layer, so the profile is kept. Users that shadow the profile with
+/// their own copy aren't affected.
+pub fn ensure_no_user_dependents(
+    layers: &Layers,
+    base_path: &str,
+    name: &str,
+    references: impl Fn(&ProfileDefinition) -> bool,
+) -> Result<(), StoreError> {
+    if layers.user().is_some() {
+        return Ok(());
+    }
+
+    let mut dependents = Vec::new();
+    for uid in layers::user_ids() {
+        let user_layers = Layers::for_user(uid);
+        if !matches!(
+            user_layers.resolve(base_path, name),
+            Ok((ProfileOrigin::System, _))
+        ) {
+            continue;
+        }
+        let dir = layers::dir(ProfileOrigin::User(uid), &PROFILE_DIR);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(profile) = file_name.strip_suffix(".json") else {
+                continue;
+            };
+            if profile.starts_with('.') {
+                continue;
+            }
+            match read_profile_json_sync::<ProfileDefinition>(ProfileKind::Global, entry.path()) {
+                Ok(info) if references(&info) => dependents.push(format!("{profile} (user {uid})")),
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!("Failed to read profile `{file_name}` of user {uid}: `{err}`")
+                }
+            }
+        }
+    }
+
+    if dependents.is_empty() {
+        Ok(())
+    } else {
+        Err(StoreError::ProfileInUse(format!(
+            "Profile `{name}` is used by the global profile(s) of other users: {}",
+            dependents.join(", ")
+        )))
+    }
+}
+
+/// Fail with [`StoreError::ProfileInUse`] if the profile
+/// is referenced by any global profile.
+pub async fn ensure_unused(
+    layers: &Layers,
+    name: &str,
+    references: impl Fn(&ProfileDefinition) -> bool,
+) -> Result<(), StoreError> {
+    let dependents = dependents(layers, references).await?;
+    if dependents.is_empty() {
+        Ok(())
+    } else {
+        Err(StoreError::ProfileInUse(format!(
+            "Profile `{name}` is used by the global profile(s): {}",
+            dependents.join(", ")
+        )))
+    }
+}
+
+/// Remove a fan or LED profile of the writable layer and handle the
+/// global profiles that reference it according to the [`RemovalStrategy`].
+/// Returns whether the active global profile was changed.
+pub async fn remove_referenced(
+    layers: &Layers,
+    base_path: &str,
+    name: &str,
+    strategy: &RemovalStrategy,
+    references: impl Fn(&ProfileDefinition) -> bool,
+    replace: impl Fn(&mut ProfileDefinition, &str),
+) -> Result<bool, StoreError> {
+    layers.ensure_writable(base_path, name)?;
+    ensure_no_user_dependents(layers, base_path, name, &references)?;
+    let profile_dir = layers.writable_dir(&PROFILE_DIR)?;
+
+    let mut transaction = Transaction::default();
+    let mut active_changed = false;
+    match strategy {
+        RemovalStrategy::Refuse => ensure_unused(layers, name, references).await?,
+        RemovalStrategy::Cascade => {
+            let active_chain = Profile::get_active_profile_chain();
+            let removed = dependents(layers, &references).await?;
+            for dependent in &removed {
+                if active_chain.contains(dependent) {
+                    return Err(StoreError::ProfileInUse(format!(
+                        "Profile `{name}` is used by the global profile `{dependent}`, \
+                        which is part of the active global profile"
+                    )));
+                }
+                ensure_writable_dependent(layers, name, dependent)?;
+                ensure_not_inherited(layers, dependent, &removed).await?;
+                let activators = activators(layers, dependent).await;
+                if !activators.is_empty() {
+                    return Err(StoreError::ProfileInUse(format!(
+                        "Profile `{name}` is used by the global profile `{dependent}`, \
+                        which is activated by {}",
+                        activators.join(", ")
+                    )));
+                }
+                transaction.remove(&profile_dir, dependent)?;
+            }
+        }
+        RemovalStrategy::Replace(replacement) => {
+            if replacement == name || layers.resolve(base_path, replacement).is_err() {
+                return Err(fdo::Error::InvalidArgs(format!(
+                    "Invalid replacement profile `{replacement}`"
+                ))
+                .into());
+            }
+
+            let active = Profile::get_active_profile_name().await.ok();
+            for dependent in dependents(layers, &references).await? {
+                ensure_writable_dependent(layers, name, &dependent)?;
+                let mut info =
+                    read_profile_json::<ProfileDefinition>(layers, &PROFILE_DIR, &dependent)
+                        .await?;
+                replace(&mut info, replacement);
+                transaction
+                    .write_json(&profile_dir, &dependent, &info)
+                    .await?;
+                active_changed |= Some(&dependent) == active.as_ref();
+            }
+        }
+    }
+
+    transaction.remove(&layers.writable_dir(base_path)?, name)?;
+    transaction.commit().await?;
+    Ok(active_changed)
+}
+
+/// Remove a global profile of the writable layer. Profiles that inherit
+/// from it take over the fields they inherited and the base of the
+/// removed profile, so they resolve to the same settings as before.
+pub async fn remove_global_profile(layers: &Layers, name: &str) -> Result<(), StoreError> {
+    layers.ensure_writable(&PROFILE_DIR, name)?;
+    ensure_no_user_dependents(layers, &PROFILE_DIR, name, |info| {
+        info.base.as_deref() == Some(name)
+    })?;
+    let profile_dir = layers.writable_dir(&PROFILE_DIR)?;
+    let removed: ProfileDefinition = read_profile_json(layers, &PROFILE_DIR, name).await?;
+    let activators = activators(layers, name).await;
+    if !activators.is_empty() {
+        return Err(StoreError::ProfileInUse(format!(
+            "Profile `{name}` is activated by {}",
+            activators.join(", ")
+        )));
+    }
+
+    let mut transaction = Transaction::default();
+    for dependent in dependents(layers, |info| info.base.as_deref() == Some(name)).await? {
+        ensure_writable_dependent(layers, name, &dependent)?;
+        let mut info =
+            read_profile_json::<ProfileDefinition>(layers, &PROFILE_DIR, &dependent).await?;
+        info.detach(removed.clone());
+        transaction
+            .write_json(&profile_dir, &dependent, &info)
+            .await?;
+    }
+
+    transaction.remove(&profile_dir, name)?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Rename a global profile of the writable layer
+/// and the references of the profiles that inherit from it.
+pub async fn rename_global_profile(
+    layers: &Layers,
+    from: &str,
+    to: &str,
+) -> Result<(), StoreError> {
+    layers.ensure_writable(&PROFILE_DIR, from)?;
+    ensure_no_user_dependents(layers, &PROFILE_DIR, from, |info| {
+        info.base.as_deref() == Some(from)
+    })?;
+    let profile_dir = layers.writable_dir(&PROFILE_DIR)?;
+
+    let mut transaction = Transaction::default();
+    for dependent in dependents(layers, |info| info.base.as_deref() == Some(from)).await? {
+        ensure_writable_dependent(layers, from, &dependent)?;
+        let mut info =
+            read_profile_json::<ProfileDefinition>(layers, &PROFILE_DIR, &dependent).await?;
+        info.base = Some(to.to_owned());
+        transaction
+            .write_json(&profile_dir, &dependent, &info)
+            .await?;
+    }
+
+    // Schedules and rules are resolved in the system layer.
+    if layers.user().is_none() {
+        for (name, mut schedule) in schedule::load_schedules().await {
+            if schedule.profile == from {
+                to.clone_into(&mut schedule.profile);
+                let data = serde_json::to_vec_pretty(&schedule).unwrap();
+                transaction.write(&SCHEDULE_DIR, &name, &data).await?;
+            }
+        }
+        for (name, mut rule) in rules::load_rules().await {
+            if rule.profile == from {
+                to.clone_into(&mut rule.profile);
+                let data = serde_json::to_vec_pretty(&rule).unwrap();
+                transaction.write(&RULE_DIR, &name, &data).await?;
+            }
+        }
+    }
+
+    transaction.rename(&profile_dir, from, to)?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// The schedules and process rules that activate a global profile.
+/// They are resolved in the system layer, so only changes of it affect them.
+async fn activators(layers: &Layers, name: &str) -> Vec<String> {
+    if layers.user().is_some() {
+        return Vec::new();
+    }
+    let schedules = schedule::load_schedules()
+        .await
+        .into_iter()
+        .filter(|(_, schedule)| schedule.profile == name)
+        .map(|(schedule, _)| format!("schedule `{schedule}`"));
+    let rules = rules::load_rules()
+        .await
+        .into_iter()
+        .filter(|(_, rule)| rule.profile == name)
+        .map(|(rule, _)| format!("rule `{rule}`"));
+    schedules.chain(rules).collect()
+}
+
+/// Fail with [`StoreError::ProfileInUse`] if a global profile that isn't
+/// removed as well inherits from a profile that is removed by a cascade.
+///
+/// Detaching it would copy the reference to the removed fan or LED
+/// profile that it inherited, so the profiles are kept instead.
+async fn ensure_not_inherited(
+    layers: &Layers,
+    name: &str,
+    removed: &[String],
+) -> Result<(), StoreError> {
+    let inherits = |info: &ProfileDefinition| info.base.as_deref() == Some(name);
+    ensure_no_user_dependents(layers, &PROFILE_DIR, name, inherits)?;
+    let heirs: Vec<_> = dependents(layers, inherits)
+        .await?
+        .into_iter()
+        .filter(|heir| !removed.contains(heir))
+        .collect();
+    if heirs.is_empty() {
+        Ok(())
+    } else {
+        Err(StoreError::ProfileInUse(format!(
+            "Global profile `{name}` can't be removed, the global profile(s) {} inherit from it",
+            heirs.join(", ")
+        )))
+    }
+}
+
+/// Dependents of lower layers can't be changed, so the profile must be kept.
+fn ensure_writable_dependent(
+    layers: &Layers,
+    name: &str,
+    dependent: &str,
+) -> Result<(), StoreError> {
+    layers
+        .ensure_writable(&PROFILE_DIR, dependent)
+        .map_err(|_| {
+            StoreError::ProfileInUse(format!(
+                "Profile `{name}` is used by the read-only global profile `{dependent}`"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use tailor_api::{upgrade_profile, ProfileDefinition, ProfileKind, SCHEMA_VERSION};
+
+    use super::{Operation, Transaction};
+
+    #[test]
+    fn transaction_writes_versioned_files() {
+        let dir = std::env::temp_dir().join(format!("tailord-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        tokio_uring::start(async {
+            let mut transaction = Transaction::default();
+            transaction
+                .write_json(
+                    dir.to_str().unwrap(),
+                    "silent",
+                    &ProfileDefinition::default(),
+                )
+                .await
+                .unwrap();
+
+            let Operation::Write { temp, .. } = &transaction.operations[0] else {
+                panic!("Expected a write");
+            };
+            let data = std::fs::read_to_string(temp).unwrap();
+            let upgraded = upgrade_profile(ProfileKind::Global, &data).unwrap();
+            assert_eq!(upgraded.from_version, SCHEMA_VERSION);
+        });
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
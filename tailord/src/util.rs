@@ -1,4 +1,9 @@
-use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
 use zbus::fdo;
 
 pub fn normalize_json_path(base_path: &str, name: &str) -> fdo::Result<String> {
@@ -19,34 +24,47 @@ pub fn normalize_json_path(base_path: &str, name: &str) -> fdo::Result<String> {
     }
 }
 
-pub async fn write_file(base_path: &str, name: &str, data: &[u8]) -> Result<(), fdo::Error> {
-    tokio::fs::write(normalize_json_path(base_path, name)?, data)
-        .await
-        .map_err(|err| fdo::Error::IOError(err.to_string()))
+/// Makes the names of temporary files unique within the process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A new temporary file next to `path` that is used for atomic writes.
+/// Each call returns another name, so concurrent writes don't interfere.
+/// Hidden files are ignored by [`get_profiles`].
+pub fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{file_name}.{}.{id}.tmp", std::process::id()))
 }
 
-pub async fn write_json<T: Serialize>(
-    base_path: &str,
-    name: &str,
-    data: &T,
-) -> Result<(), fdo::Error> {
-    write_file(base_path, name, to_pretty_json(data)?.as_bytes()).await
+/// Write the data to a temporary file and flush it to the disk.
+/// Returns the path of the temporary file.
+pub async fn write_temp_file(path: &Path, data: &[u8]) -> Result<PathBuf, io::Error> {
+    let temp = temp_path(path);
+    tokio::fs::write(&temp, data).await?;
+    tokio::fs::File::open(&temp).await?.sync_all().await?;
+    Ok(temp)
 }
 
-pub fn write_json_sync<T: Serialize>(
-    base_path: &str,
-    name: &str,
-    data: &T,
-) -> Result<(), fdo::Error> {
-    std::fs::write(
-        normalize_json_path(base_path, name)?,
-        to_pretty_json(data)?.as_bytes(),
-    )
-    .map_err(|err| fdo::Error::IOError(err.to_string()))
+/// Write to a temporary file and rename it afterwards,
+/// so a crash never leaves a truncated file behind.
+pub async fn write_atomic(path: &Path, data: &[u8]) -> Result<(), io::Error> {
+    let temp = write_temp_file(path, data).await?;
+    tokio::fs::rename(temp, path).await
 }
 
-fn to_pretty_json<T: Serialize>(data: &T) -> Result<String, fdo::Error> {
-    serde_json::to_string_pretty(data).map_err(|err| fdo::Error::Failed(err.to_string()))
+pub async fn write_file(base_path: &str, name: &str, data: &[u8]) -> Result<(), fdo::Error> {
+    write_atomic(Path::new(&normalize_json_path(base_path, name)?), data)
+        .await
+        .map_err(|err| fdo::Error::IOError(err.to_string()))
+}
+
+pub fn write_file_sync(base_path: &str, name: &str, data: &[u8]) -> Result<(), fdo::Error> {
+    let path = PathBuf::from(normalize_json_path(base_path, name)?);
+    let temp = temp_path(&path);
+    std::fs::write(&temp, data)
+        .and_then(|_| std::fs::File::open(&temp)?.sync_all())
+        .and_then(|_| std::fs::rename(&temp, &path))
+        .map_err(|err| fdo::Error::IOError(err.to_string()))
 }
 
 pub async fn read_file(base_path: &str, name: &str) -> Result<String, fdo::Error> {
@@ -55,11 +73,6 @@ pub async fn read_file(base_path: &str, name: &str) -> Result<String, fdo::Error
         .map_err(|err| fdo::Error::IOError(err.to_string()))
 }
 
-pub async fn read_json<T: DeserializeOwned>(base_path: &str, name: &str) -> Result<T, fdo::Error> {
-    let data = read_file(base_path, name).await?;
-    serde_json::from_str(&data).map_err(|err| fdo::Error::Failed(err.to_string()))
-}
-
 pub async fn remove_file(base_path: &str, name: &str) -> Result<(), fdo::Error> {
     tokio::fs::remove_file(normalize_json_path(base_path, name)?)
         .await
@@ -90,7 +103,10 @@ pub async fn get_profiles(base_path: &str) -> fdo::Result<Vec<String>> {
         {
             match entry.file_name().into_string() {
                 Ok(file_name) => {
-                    if file_name.contains(".json") {
+                    if file_name.starts_with('.') {
+                        // Temporary files of unfinished writes.
+                        continue;
+                    } else if file_name.contains(".json") {
                         if !file_name.contains("active_profile") {
                             entries.push(file_name.replace(".json", ""))
                         }
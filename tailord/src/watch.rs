@@ -0,0 +1,312 @@
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use signal_hook::consts::SIGHUP;
+use signal_hook_tokio::Signals;
+use tailor_api::{ColorProfile, ProfileDefinition, ProfileKind, ProfileOrigin};
+use tokio::sync::mpsc;
+use zbus::fdo;
+
+use crate::{
+    fancontrol::profile::FanProfile,
+    layers,
+    profiles::{FAN_DIR, KEYBOARD_DIR, PROFILE_DIR},
+    state::STATE_PATH,
+    store,
+};
+
+/// Editors and configuration management tools often
+/// write a file in multiple steps. Wait until they are done.
+const DEBOUNCE_TIME: Duration = Duration::from_millis(300);
+const EVENT_BUFFER_SIZE: usize = 4096;
+const WATCH_MASK: u32 = libc::IN_CLOSE_WRITE
+    | libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO;
+
+/// A change of the profiles on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileChange {
+    /// A reload was requested with `SIGHUP` or events were lost.
+    Active,
+    /// The state file was written.
+    State,
+    /// A profile file was written, created or removed.
+    Profile {
+        kind: ProfileKind,
+        name: String,
+        path: PathBuf,
+    },
+}
+
+impl ProfileChange {
+    /// Check that a changed profile can be loaded.
+    /// Removed profiles are always valid.
+    pub fn validate(&self) -> fdo::Result<()> {
+        let ProfileChange::Profile { kind, path, .. } = self else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+        match kind {
+            ProfileKind::Global => {
+                store::read_profile_json_sync::<ProfileDefinition>(*kind, path).map(drop)
+            }
+            ProfileKind::Led => {
+                store::read_profile_json_sync::<ColorProfile>(*kind, path).map(drop)
+            }
+            ProfileKind::Fan => FanProfile::load_config(path).map(drop),
+        }
+    }
+}
+
+/// Watches the profile directories and the active profile with inotify.
+///
+/// Only directories that exist at startup are watched,
+/// so the profiles of new users are picked up after a restart.
+pub struct ProfileWatcher {
+    receiver: mpsc::UnboundedReceiver<ProfileChange>,
+    signals: Signals,
+}
+
+impl ProfileWatcher {
+    /// Start watching. The inotify events are read
+    /// on a separate thread, so this doesn't block the async runtime.
+    pub fn new() -> Result<Self, io::Error> {
+        let fd = init_inotify()?;
+        let mut dirs = HashMap::new();
+        for dir in watched_dirs() {
+            match add_watch(&fd, &dir) {
+                Ok(wd) => {
+                    dirs.insert(wd, dir);
+                }
+                Err(err) => {
+                    tracing::debug!("Not watching `{}`: `{err}`", dir.display());
+                }
+            }
+        }
+
+        let signals = Signals::new([SIGHUP])?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        std::thread::Builder::new()
+            .name("profile-watcher".to_owned())
+            .spawn(move || {
+                let mut buffer = vec![0; EVENT_BUFFER_SIZE];
+                loop {
+                    // SAFETY: The buffer is valid for its whole length.
+                    let len = unsafe {
+                        libc::read(fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len())
+                    };
+                    if len < 0 {
+                        let err = io::Error::last_os_error();
+                        if err.kind() == io::ErrorKind::Interrupted {
+                            continue;
+                        }
+                        tracing::error!("Stop watching for profile changes: `{err}`");
+                        break;
+                    }
+
+                    for (wd, mask, name) in parse_events(&buffer[..len as usize]) {
+                        let change = if mask & libc::IN_Q_OVERFLOW != 0 {
+                            // Events were lost, so reload everything.
+                            Some(ProfileChange::Active)
+                        } else {
+                            dirs.get(&wd).and_then(|dir| classify(dir, &name))
+                        };
+                        if let Some(change) = change {
+                            if sender.send(change).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { receiver, signals })
+    }
+
+    /// Wait for the next changes and remove duplicates.
+    /// Returns [`None`] if the events can't be received anymore.
+    pub async fn next_changes(&mut self) -> Option<Vec<ProfileChange>> {
+        let first = tokio::select! {
+            change = self.receiver.recv() => change?,
+            Some(_) = self.signals.next() => ProfileChange::Active,
+        };
+
+        let mut changes = vec![first];
+        tokio::time::sleep(DEBOUNCE_TIME).await;
+        while let Ok(change) = self.receiver.try_recv() {
+            if !changes.contains(&change) {
+                changes.push(change);
+            }
+        }
+        Some(changes)
+    }
+}
+
+/// The profile directories of all layers and the directory of the state file.
+fn watched_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for base_path in [
+        PROFILE_DIR.as_str(),
+        KEYBOARD_DIR.as_str(),
+        FAN_DIR.as_str(),
+    ] {
+        dirs.push(PathBuf::from(layers::dir(ProfileOrigin::Vendor, base_path)));
+        dirs.extend(
+            layers::writable_dirs(base_path)
+                .into_iter()
+                .map(PathBuf::from),
+        );
+    }
+    if let Some(state_dir) = Path::new(STATE_PATH.as_str()).parent() {
+        dirs.push(state_dir.to_owned());
+    }
+    dirs
+}
+
+fn init_inotify() -> Result<OwnedFd, io::Error> {
+    // SAFETY: Plain system call, the file descriptor is owned by the returned value.
+    unsafe {
+        let fd = libc::inotify_init1(libc::IN_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+fn add_watch(fd: &OwnedFd, dir: &Path) -> Result<i32, io::Error> {
+    let path = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    // SAFETY: The path is a valid C string.
+    let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), WATCH_MASK) };
+    if wd < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(wd)
+    }
+}
+
+/// Split the buffer of an inotify read into
+/// the watch descriptor, mask and file name of each event.
+fn parse_events(buffer: &[u8]) -> Vec<(i32, u32, String)> {
+    let header_size = std::mem::size_of::<libc::inotify_event>();
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset + header_size <= buffer.len() {
+        // SAFETY: The header is inside of the buffer, which isn't necessarily aligned.
+        let event: libc::inotify_event =
+            unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+        let name_start = offset + header_size;
+        let name_end = (name_start + event.len as usize).min(buffer.len());
+        // The name is padded with null bytes.
+        let name = buffer[name_start..name_end]
+            .split(|byte| *byte == 0)
+            .next()
+            .unwrap_or_default();
+        events.push((
+            event.wd,
+            event.mask,
+            String::from_utf8_lossy(name).into_owned(),
+        ));
+        offset = name_end;
+    }
+    events
+}
+
+/// Map a changed file to a profile change.
+/// Returns [`None`] for unrelated and temporary files.
+fn classify(dir: &Path, file_name: &str) -> Option<ProfileChange> {
+    // Temporary files of the daemon start with a dot, backups of editors end with a tilde.
+    if file_name.starts_with('.') || file_name.ends_with('~') {
+        return None;
+    }
+
+    let path = dir.join(file_name);
+    if path == Path::new(STATE_PATH.as_str()) {
+        return Some(ProfileChange::State);
+    }
+
+    let name = file_name.strip_suffix(".json")?;
+    let kind = match dir.file_name()?.to_str()? {
+        "profiles" => ProfileKind::Global,
+        "keyboard" => ProfileKind::Led,
+        "fan" => ProfileKind::Fan,
+        _ => return None,
+    };
+    Some(ProfileChange::Profile {
+        kind,
+        name: name.to_owned(),
+        path,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use tailor_api::ProfileKind;
+
+    use super::{classify, parse_events, ProfileChange};
+
+    #[test]
+    fn classify_events() {
+        let mut buffer = Vec::new();
+        for (wd, name) in [(1, "silent.json"), (2, "")] {
+            let mut padded = name.as_bytes().to_vec();
+            padded.resize((name.len() + 15) / 16 * 16, 0);
+            let event = libc::inotify_event {
+                wd,
+                mask: libc::IN_CLOSE_WRITE,
+                cookie: 0,
+                len: padded.len() as u32,
+            };
+            // SAFETY: The event is plain old data.
+            buffer.extend_from_slice(unsafe {
+                std::slice::from_raw_parts(
+                    (&event as *const libc::inotify_event).cast::<u8>(),
+                    std::mem::size_of::<libc::inotify_event>(),
+                )
+            });
+            buffer.extend(padded);
+        }
+        assert_eq!(
+            parse_events(&buffer),
+            [
+                (1, libc::IN_CLOSE_WRITE, "silent.json".to_owned()),
+                (2, libc::IN_CLOSE_WRITE, String::new())
+            ]
+        );
+
+        let dir = Path::new("/etc/tailord/users/1000/fan");
+        assert_eq!(
+            classify(dir, "silent.json"),
+            Some(ProfileChange::Profile {
+                kind: ProfileKind::Fan,
+                name: "silent".to_owned(),
+                path: PathBuf::from("/etc/tailord/users/1000/fan/silent.json"),
+            })
+        );
+        assert_eq!(classify(dir, ".silent.json.tmp"), None);
+        assert_eq!(classify(dir, "silent.json~"), None);
+        assert_eq!(
+            classify(Path::new("/var/lib/tailord"), "state.json"),
+            Some(ProfileChange::State)
+        );
+        assert_eq!(classify(Path::new("/etc/tailord"), "tailord.toml"), None);
+    }
+}
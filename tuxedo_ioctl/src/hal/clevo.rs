@@ -1,10 +1,14 @@
 use crate::{error::IoctlError, read, write};
 
 use super::traits::{HardwareDevice, WebcamDevice};
-use super::IoctlResult;
+use super::{FanRole, IoctlResult};
 
 pub const MAX_FAN_SPEED: u8 = 0xff;
 
+/// The roles of the fans by index. The role of the
+/// third fan of some devices isn't known.
+const FAN_ROLES: [FanRole; 2] = [FanRole::Cpu, FanRole::Gpu];
+
 const PERF_PROF_QUIET: &str = "quiet";
 const PERF_PROF_POWERSAVE: &str = "power_saving";
 const PERF_PROF_PERFORMANCE: &str = "performance";
@@ -61,6 +65,14 @@ impl HardwareDevice for ClevoHardware {
         self.num_of_fans
     }
 
+    fn get_fan_role(&self, fan: u8) -> Option<FanRole> {
+        if fan < self.num_of_fans {
+            FAN_ROLES.get(fan as usize).copied()
+        } else {
+            None
+        }
+    }
+
     fn set_fans_auto(&self) -> IoctlResult<()> {
         write::cl::fan_auto(&self.file, 0xF)
     }
@@ -14,6 +14,14 @@ mod uniwill;
 
 pub type IoctlResult<T> = Result<T, IoctlError>;
 
+/// What a fan cools. Fans with the same role might
+/// have different indices in other hardware families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FanRole {
+    Cpu,
+    Gpu,
+}
+
 #[derive(Debug)]
 pub struct IoInterface {
     pub module_version: String,
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use super::IoctlResult;
+use super::{FanRole, IoctlResult};
 
 pub trait HardwareDevice: Send + Sync + Debug {
     fn device_interface_id_str(&self) -> IoctlResult<String>;
@@ -10,6 +10,9 @@ pub trait HardwareDevice: Send + Sync + Debug {
     // Get the amount of available fans
     fn get_number_fans(&self) -> u8;
 
+    /// Get the role of an available fan or `None` if it's unknown.
+    fn get_fan_role(&self, fan: u8) -> Option<FanRole>;
+
     fn set_fans_auto(&self) -> IoctlResult<()>;
 
     /// Set the fan speed in percent from 0 to 100.
@@ -1,10 +1,13 @@
 use crate::{error::IoctlError, read, write};
 
 use super::traits::{HardwareDevice, TdpDevice};
-use super::IoctlResult;
+use super::{FanRole, IoctlResult};
 
 const MAX_FAN_SPEED: u8 = 0xc8;
 
+/// The roles of the fans by index.
+const FAN_ROLES: [FanRole; 2] = [FanRole::Cpu, FanRole::Gpu];
+
 const PERF_PROF_BALANCED: &str = "power_save";
 const PERF_PROF_ENTHUSIAST: &str = "enthusiast";
 const PERF_PROF_OVERBOOST: &str = "overboost";
@@ -69,6 +72,14 @@ impl HardwareDevice for UniwillHardware {
         self.num_of_fans
     }
 
+    fn get_fan_role(&self, fan: u8) -> Option<FanRole> {
+        if fan < self.num_of_fans {
+            FAN_ROLES.get(fan as usize).copied()
+        } else {
+            None
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     fn set_fans_auto(&self) -> IoctlResult<()> {
         write::uw::fan_auto(&self.file, 0)?;
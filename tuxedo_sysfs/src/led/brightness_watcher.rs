@@ -0,0 +1,60 @@
+use std::{io, path::Path, time::Duration};
+
+use crate::sysfs_util::{r_file, read_int_list};
+
+use super::BrightnessWatcher;
+
+/// Returned by the kernel as long as the firmware didn't change the brightness.
+const ENODATA: i32 = 61;
+
+impl BrightnessWatcher {
+    /// Open the `brightness_hw_changed` file of an LED device.
+    /// Returns [`None`] if the device doesn't support it.
+    pub(super) async fn new(path: impl AsRef<Path>, max_brightness: u32) -> Option<Self> {
+        let file = r_file(path).await.ok()?;
+        let mut this = Self {
+            max_brightness,
+            file,
+            last_value: None,
+        };
+        // Ignore changes that happened before startup.
+        this.last_value = this.read_value().await.ok()?;
+        Some(this)
+    }
+
+    /// Poll the device in the given interval until the firmware
+    /// changes the brightness and return the new brightness in percent.
+    pub async fn wait_for_change(&mut self, interval: Duration) -> Result<u8, io::Error> {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            if let Some(value) = self.read_value().await? {
+                if Some(value) != self.last_value {
+                    self.last_value = Some(value);
+                    return Ok(self.to_percent(value));
+                }
+            }
+        }
+    }
+
+    async fn read_value(&mut self) -> Result<Option<u32>, io::Error> {
+        match read_int_list(&mut self.file).await {
+            Ok(values) => Ok(Some(values[0])),
+            Err(err) if err.raw_os_error() == Some(ENODATA) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn to_percent(&self, value: u32) -> u8 {
+        let percent = (value as f32 / self.max_brightness as f32 * 100.0)
+            .round()
+            .clamp(0.0, 100.0) as u8;
+
+        // Don't turn the device off if the firmware only dimmed it.
+        if value > 0 {
+            percent.max(1)
+        } else {
+            0
+        }
+    }
+}
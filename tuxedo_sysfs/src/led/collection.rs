@@ -1,19 +1,21 @@
 use std::{
     io,
     ops::{Index, IndexMut},
+    path::Path,
 };
 
-use tailor_api::Color;
+use tailor_api::{Color, LedChannel};
 
 use crate::sysfs_util::{r_file, read_int_list, read_path_to_string, rw_file};
 
-use super::{Collection, Controller};
+use super::{BrightnessWatcher, Collection, Controller};
 
 const SYSFS_LED_PATH: &str = "/sys/class/leds";
 const BRIGHTNESS: &str = "brightness";
 const MAX_BRIGHTNESS: &str = "max_brightness";
 const MULTI_INDEX: &str = "multi_index";
 const MULTI_INTENSITIES: &str = "multi_intensity";
+const BRIGHTNESS_HW_CHANGED: &str = "brightness_hw_changed";
 const DEVICE_NAME: &str = "device/name";
 const DEVICE_MODALIAS: &str = "device/modalias";
 
@@ -23,123 +25,30 @@ impl Collection {
 
         let mut dirs = tokio::fs::read_dir(SYSFS_LED_PATH).await?;
         while let Some(dir) = dirs.next_entry().await? {
-            let path = dir.path();
-            let file_name = path
-                .file_name()
-                .expect("The sysfs path must have a last segment");
-            let file_name_str = file_name.to_str().unwrap_or_default();
-
-            if file_name_str.contains("mmc") {
-                // Not a useful device, skip it.
-                continue;
+            if let Some(controller) = load_node(&dir.path()).await? {
+                controllers.push(controller);
             }
+        }
 
-            let function = if let Some(function) = file_name_str.split(':').last() {
-                function.trim().to_owned()
-            } else {
-                tracing::warn!("Badly formatted led device: {:?}", file_name);
-                continue;
-            };
-
-            let device_name_path = path.join(DEVICE_NAME);
-            let device_name = if let Ok(name) = read_path_to_string(device_name_path).await {
-                name.trim().to_owned()
-            } else {
-                let device_modalias_path = path.join(DEVICE_MODALIAS);
-                if let Ok(name) = read_path_to_string(device_modalias_path).await {
-                    name.trim().to_owned()
-                } else {
-                    tracing::warn!("Could not find LED device name: {:?}", file_name);
-                    continue;
-                }
-            };
-
-            // Check for brightness file
-            let brightness_path = path.join(BRIGHTNESS);
-            let brightness_file = if let Ok(file) = rw_file(brightness_path).await {
-                file
-            } else {
-                // Not even basic support available -> skip device.
-                continue;
-            };
-
-            // Get maximum brightness
-            let max_brightness_path = path.join(MAX_BRIGHTNESS);
-            let max_brightness = if let Ok(mut file) = r_file(max_brightness_path).await {
-                if let Ok(values) = read_int_list(&mut file).await {
-                    values[0]
-                } else {
-                    tracing::warn!("Brightness file can't be read: {:?}", file_name);
-                    continue;
-                }
-            } else {
-                // Not even basic support available -> skip device.
-                continue;
-            };
-
-            if max_brightness < 2 {
-                // Not even basic support available -> skip device.
-                continue;
-            }
+        Ok(Self {
+            controllers: group_zones(controllers),
+        })
+    }
 
-            let multi_index_path = path.join(MULTI_INDEX);
-            let rgb_support = tokio::fs::read_to_string(multi_index_path)
-                .await
-                .map(|content| content.trim().to_lowercase() == "red green blue")
-                .unwrap_or_default();
-
-            if rgb_support {
-                // Get intensities
-                let intensities_path = path.join(MULTI_INTENSITIES);
-                let (intensities_file, intensities) =
-                    if let Ok(mut file) = rw_file(intensities_path).await {
-                        if let Ok(values) = read_int_list(&mut file).await {
-                            (file, values)
-                        } else {
-                            tracing::warn!("Intensities file can't be read: {:?}", file_name);
-                            continue;
-                        }
-                    } else {
-                        // Should be there for an RGB device
-                        tracing::warn!(
-                            "RGB device should have multiple intensities: {:?}",
-                            file_name
-                        );
-                        continue;
-                    };
-
-                if intensities.len() == 3 {
-                    // Push controller with RGB capabilities
-                    controllers.push(
-                        Controller::new_rgb(
-                            max_brightness,
-                            device_name,
-                            function,
-                            brightness_file,
-                            intensities_file,
-                        )
-                        .await?,
-                    );
-                } else {
-                    // Should be 3 for an RGB device
-                    tracing::warn!("RGB device should have 3 intensities: {:?}", file_name);
-                    continue;
-                }
-            } else {
-                // Push controller with monochrome capabilities
-                controllers.push(
-                    Controller::new_monochrome(
-                        max_brightness,
-                        device_name,
-                        function,
-                        brightness_file,
-                    )
-                    .await?,
-                );
+    /// Load only the given LED nodes, for example
+    /// after they were reported by a [`HotplugMonitor`](super::HotplugMonitor).
+    pub async fn from_nodes(names: &[String]) -> Result<Self, io::Error> {
+        let mut controllers = Vec::new();
+        for name in names {
+            let path = Path::new(SYSFS_LED_PATH).join(name);
+            if let Some(controller) = load_node(&path).await? {
+                controllers.push(controller);
             }
         }
 
-        Ok(Self { controllers })
+        Ok(Self {
+            controllers: group_zones(controllers),
+        })
     }
 
     pub async fn set_color_all(&mut self, color: &Color) -> Result<(), io::Error> {
@@ -170,6 +79,170 @@ impl Collection {
     }
 }
 
+/// Create a controller for a single LED node.
+/// Returns [`None`] if the node isn't supported.
+async fn load_node(path: &Path) -> Result<Option<Controller>, io::Error> {
+    let file_name = path
+        .file_name()
+        .expect("The sysfs path must have a last segment");
+    let file_name_str = file_name.to_str().unwrap_or_default();
+
+    if file_name_str.contains("mmc") {
+        // Not a useful device, skip it.
+        return Ok(None);
+    }
+
+    let function = if let Some(function) = file_name_str.split(':').last() {
+        function.trim().to_owned()
+    } else {
+        tracing::warn!("Badly formatted led device: {:?}", file_name);
+        return Ok(None);
+    };
+
+    let device_name_path = path.join(DEVICE_NAME);
+    let device_name = if let Ok(name) = read_path_to_string(device_name_path).await {
+        name.trim().to_owned()
+    } else {
+        let device_modalias_path = path.join(DEVICE_MODALIAS);
+        if let Ok(name) = read_path_to_string(device_modalias_path).await {
+            name.trim().to_owned()
+        } else {
+            tracing::warn!("Could not find LED device name: {:?}", file_name);
+            return Ok(None);
+        }
+    };
+
+    // Check for brightness file
+    let brightness_path = path.join(BRIGHTNESS);
+    let brightness_file = if let Ok(file) = rw_file(brightness_path).await {
+        file
+    } else {
+        // Not even basic support available -> skip device.
+        return Ok(None);
+    };
+
+    // Get maximum brightness
+    let max_brightness_path = path.join(MAX_BRIGHTNESS);
+    let max_brightness = if let Ok(mut file) = r_file(max_brightness_path).await {
+        if let Ok(values) = read_int_list(&mut file).await {
+            values[0]
+        } else {
+            tracing::warn!("Brightness file can't be read: {:?}", file_name);
+            return Ok(None);
+        }
+    } else {
+        // Not even basic support available -> skip device.
+        return Ok(None);
+    };
+
+    if max_brightness < 2 {
+        // Not even basic support available -> skip device.
+        return Ok(None);
+    }
+
+    let multi_index_path = path.join(MULTI_INDEX);
+    let channels: Vec<LedChannel> = tokio::fs::read_to_string(multi_index_path)
+        .await
+        .map(|content| {
+            content
+                .split_whitespace()
+                .map(LedChannel::from_sysfs_name)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !channels.is_empty() {
+        // Get intensities
+        let intensities_path = path.join(MULTI_INTENSITIES);
+        let (intensities_file, intensities) = if let Ok(mut file) = rw_file(intensities_path).await
+        {
+            if let Ok(values) = read_int_list(&mut file).await {
+                (file, values)
+            } else {
+                tracing::warn!("Intensities file can't be read: {:?}", file_name);
+                return Ok(None);
+            }
+        } else {
+            // Should be there for an RGB device
+            tracing::warn!(
+                "RGB device should have multiple intensities: {:?}",
+                file_name
+            );
+            return Ok(None);
+        };
+
+        if intensities.len() == channels.len() {
+            // Push controller with multicolor capabilities
+            let mut controller = Controller::new_multicolor(
+                max_brightness,
+                device_name,
+                function,
+                brightness_file,
+                intensities_file,
+                channels,
+            )
+            .await?;
+            controller.brightness_watcher =
+                BrightnessWatcher::new(path.join(BRIGHTNESS_HW_CHANGED), max_brightness).await;
+            controller.zones[0].name = file_name_str.to_owned();
+            Ok(Some(controller))
+        } else {
+            tracing::warn!(
+                "Multicolor device has {} intensities for {} channels: {:?}",
+                intensities.len(),
+                channels.len(),
+                file_name
+            );
+            Ok(None)
+        }
+    } else {
+        // Push controller with monochrome capabilities
+        let mut controller =
+            Controller::new_monochrome(max_brightness, device_name, function, brightness_file)
+                .await?;
+        controller.brightness_watcher =
+            BrightnessWatcher::new(path.join(BRIGHTNESS_HW_CHANGED), max_brightness).await;
+        controller.zones[0].name = file_name_str.to_owned();
+        Ok(Some(controller))
+    }
+}
+
+/// Split the zone number from the function of an LED node,
+/// e.g. `kbd_backlight_2` becomes (`kbd_backlight`, 2).
+fn split_zone(function: &str) -> (&str, u32) {
+    if let Some((base, idx)) = function.rsplit_once('_') {
+        if let Ok(idx) = idx.parse() {
+            return (base, idx);
+        }
+    }
+    (function, 0)
+}
+
+/// Merge LED nodes of the same device that only differ by their zone number
+/// (e.g. `kbd_backlight`, `kbd_backlight_1`, ...) into a single controller.
+fn group_zones(mut controllers: Vec<Controller>) -> Vec<Controller> {
+    controllers.sort_by(|a, b| {
+        let (a_base, a_idx) = split_zone(&a.function);
+        let (b_base, b_idx) = split_zone(&b.function);
+        (&a.device_name, a_base, a_idx).cmp(&(&b.device_name, b_base, b_idx))
+    });
+
+    let mut grouped: Vec<Controller> = Vec::new();
+    for controller in controllers {
+        let base = split_zone(&controller.function).0.to_owned();
+        match grouped.last_mut() {
+            Some(prev)
+                if split_zone(&prev.function).0 == base && prev.can_merge_zones(&controller) =>
+            {
+                prev.merge_zones(controller);
+                prev.function = base;
+            }
+            _ => grouped.push(controller),
+        }
+    }
+    grouped
+}
+
 impl Index<usize> for Collection {
     type Output = Controller;
 
@@ -188,7 +261,14 @@ impl IndexMut<usize> for Collection {
 mod test {
     use tailor_api::Color;
 
-    use super::Collection;
+    use super::{split_zone, Collection};
+
+    #[test]
+    fn zone_names() {
+        assert_eq!(split_zone("kbd_backlight"), ("kbd_backlight", 0));
+        assert_eq!(split_zone("kbd_backlight_1"), ("kbd_backlight", 1));
+        assert_eq!(split_zone("kbd_backlight_12"), ("kbd_backlight", 12));
+    }
 
     #[test]
     fn test_colors() {
@@ -1,29 +1,74 @@
 use std::io;
 
 use tailor_api::Color;
-use tailor_api::LedControllerMode;
+use tailor_api::{LedChannel, LedControllerMode, LedZoneLayout};
 
 use crate::sysfs_util::{read_int_list, write_string};
 
-use super::Controller;
+use super::{BrightnessWatcher, Controller, Zone};
+
+/// Keyboards with per-key lighting have six rows of keys.
+const KEYBOARD_ROWS: u32 = 6;
+
+/// Keyboards with backlight zones have up to four of them, side by side.
+/// More zones are keys, which the drivers number row by row.
+fn zone_layout(zones: u32) -> LedZoneLayout {
+    match zones {
+        0 | 1 => LedZoneLayout::Single,
+        2..=4 => LedZoneLayout::Row,
+        zones if zones % KEYBOARD_ROWS == 0 => LedZoneLayout::Keys {
+            rows: KEYBOARD_ROWS,
+            columns: zones / KEYBOARD_ROWS,
+        },
+        _ => LedZoneLayout::Row,
+    }
+}
 
 impl Controller {
     pub async fn new_rgb(
+        max_brightness: u32,
+        device_name: String,
+        function: String,
+        brightness_file: tokio_uring::fs::File,
+        intensities_file: tokio_uring::fs::File,
+    ) -> Result<Self, io::Error> {
+        Self::new_multicolor(
+            max_brightness,
+            device_name,
+            function,
+            brightness_file,
+            intensities_file,
+            vec![LedChannel::Red, LedChannel::Green, LedChannel::Blue],
+        )
+        .await
+    }
+
+    /// Create a controller for a device with arbitrary color channels,
+    /// for example "green red blue" or RGBW.
+    pub async fn new_multicolor(
         max_brightness: u32,
         device_name: String,
         function: String,
         mut brightness_file: tokio_uring::fs::File,
         intensities_file: tokio_uring::fs::File,
+        channels: Vec<LedChannel>,
     ) -> Result<Self, io::Error> {
         // Set brightness to 100% so the individual intensities represent their colors without additional scaling.
         write_string(&mut brightness_file, max_brightness.to_string()).await?;
 
         Ok(Self {
             max_brightness,
+            brightness: 100,
+            gamma: None,
+            channels,
             device_name,
             function,
-            brightness_file,
-            intensities_file: Some(intensities_file),
+            zones: vec![Zone {
+                name: String::new(),
+                brightness_file,
+                intensities_file: Some(intensities_file),
+            }],
+            brightness_watcher: None,
         })
     }
 
@@ -35,51 +80,181 @@ impl Controller {
     ) -> Result<Self, io::Error> {
         Ok(Self {
             max_brightness,
+            brightness: 100,
+            gamma: None,
+            channels: Vec::new(),
             device_name,
             function,
-            brightness_file,
-            intensities_file: None,
+            zones: vec![Zone {
+                name: String::new(),
+                brightness_file,
+                intensities_file: None,
+            }],
+            brightness_watcher: None,
         })
     }
 
+    /// Whether the zones of another controller can be merged into this one.
+    pub(super) fn can_merge_zones(&self, other: &Controller) -> bool {
+        other.device_name == self.device_name
+            && other.max_brightness == self.max_brightness
+            && other.mode() == self.mode()
+            && other.channels == self.channels
+    }
+
+    /// Append the zones of another controller of the same device.
+    pub(super) fn merge_zones(&mut self, other: Controller) {
+        self.zones.extend(other.zones);
+    }
+
+    /// Set the color of all zones.
     pub async fn set_color(&mut self, color: &Color) -> Result<(), io::Error> {
+        for idx in 0..self.zones.len() {
+            self.set_zone_color(idx, color).await?;
+        }
+        Ok(())
+    }
+
+    /// Set the color of a single zone.
+    pub async fn set_zone_color(&mut self, zone: usize, color: &Color) -> Result<(), io::Error> {
         let Self {
             max_brightness,
+            brightness,
+            gamma,
+            channels,
+            zones,
+            ..
+        } = self;
+        let Zone {
             brightness_file,
             intensities_file,
             ..
-        } = self;
+        } = zones.get_mut(zone).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid zone {zone}"))
+        })?;
 
         if let Some(intensities) = intensities_file {
-            write_string(intensities, color.sysfs_rgb_string(*max_brightness)).await
+            write_string(
+                intensities,
+                color.sysfs_channels_string(channels, *max_brightness, *gamma),
+            )
+            .await
         } else {
+            // Monochrome devices only have the brightness file,
+            // so the brightness needs to be applied to the color.
             write_string(
                 brightness_file,
-                color.sysfs_monochrome_string(*max_brightness),
+                color
+                    .scaled(*brightness)
+                    .sysfs_monochrome_string(*max_brightness),
             )
             .await
         }
     }
 
+    /// The names of the sysfs LED nodes of all zones.
+    pub fn node_names(&self) -> impl Iterator<Item = &str> {
+        self.zones.iter().map(|zone| zone.name.as_str())
+    }
+
+    /// The number of individually controllable zones.
+    pub fn zone_count(&self) -> usize {
+        self.zones.len()
+    }
+
+    /// How the zones are arranged, derived from their number.
+    /// The zones are ordered by the number in the name of their LED node.
+    pub fn zone_layout(&self) -> LedZoneLayout {
+        zone_layout(self.zones.len() as u32)
+    }
+
+    /// Set the brightness in percent (0 to 100).
+    ///
+    /// RGB devices apply the brightness immediately through the
+    /// brightness file. Monochrome devices apply it with the next
+    /// call to [`Controller::set_color`].
+    pub async fn set_brightness(&mut self, brightness: u8) -> Result<(), io::Error> {
+        let brightness = brightness.min(100);
+        self.brightness = brightness;
+
+        if self.mode() == LedControllerMode::Rgb {
+            let value = (self.max_brightness as f32 * brightness as f32 / 100.0).round() as u32;
+            for zone in &mut self.zones {
+                write_string(&mut zone.brightness_file, value.to_string()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The brightness in percent.
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// Update the brightness in percent without writing it to the device.
+    /// This is useful if the brightness was already changed by the firmware.
+    pub fn sync_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(100);
+    }
+
+    /// Set the gamma value used to correct the non-linear brightness
+    /// of RGB LEDs. The value is applied with the next call to
+    /// [`Controller::set_color`].
+    pub fn set_gamma(&mut self, gamma: Option<f32>) {
+        self.gamma = gamma;
+    }
+
+    /// Take the [`BrightnessWatcher`] of this device.
+    /// Returns [`None`] if the device doesn't report
+    /// brightness changes made by the firmware.
+    pub fn take_brightness_watcher(&mut self) -> Option<BrightnessWatcher> {
+        self.brightness_watcher.take()
+    }
+
+    /// Read the current color of the first zone.
+    ///
+    /// For monochrome devices, the brightness is removed again
+    /// so the result can be passed to [`Controller::set_color`].
     pub async fn get_color(&mut self) -> Result<Color, io::Error> {
         let Self {
             max_brightness,
+            brightness,
+            gamma,
+            channels,
+            zones,
+            ..
+        } = self;
+        let Zone {
             brightness_file,
             intensities_file,
             ..
-        } = self;
+        } = &mut zones[0];
 
         if let Some(intensities) = intensities_file {
             let values = read_int_list(intensities).await?;
-            let values: [u32; 3] = values.try_into().map_err(|_| {
-                io::Error::new(io::ErrorKind::InvalidData, "Invalid number of values")
-            })?;
-            Ok(Color::from_sysfs_rgb_value(values, *max_brightness))
+            if values.len() != channels.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid number of values",
+                ));
+            }
+            Ok(Color::from_sysfs_channel_values(
+                &values,
+                channels,
+                *max_brightness,
+                *gamma,
+            ))
         } else {
             let value = read_int_list(brightness_file).await?[0];
+            let value = if *brightness == 0 {
+                value
+            } else {
+                (value * 100 / *brightness as u32).min(*max_brightness)
+            };
             Ok(Color::from_sysfs_rgb_value(
                 [value, value, value],
                 *max_brightness,
+                None,
             ))
         }
     }
@@ -92,8 +267,14 @@ impl Controller {
         &self.function
     }
 
+    /// The color channels of multicolor devices.
+    /// Empty for monochrome devices.
+    pub fn channels(&self) -> &[LedChannel] {
+        &self.channels
+    }
+
     pub fn mode(&self) -> LedControllerMode {
-        if self.intensities_file.is_some() {
+        if self.zones[0].intensities_file.is_some() {
             LedControllerMode::Rgb
         } else {
             LedControllerMode::Monochrome
@@ -0,0 +1,155 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use tokio::sync::mpsc;
+
+use super::{HotplugEvent, HotplugMonitor};
+
+/// Multicast group of the uevents sent by the kernel.
+const KERNEL_UEVENT_GROUP: u32 = 1;
+const UEVENT_BUFFER_SIZE: usize = 8192;
+
+impl HotplugMonitor {
+    /// Open a netlink socket for kernel uevents.
+    ///
+    /// The socket is read on a separate thread,
+    /// so this doesn't block the async runtime.
+    pub fn new() -> Result<Self, io::Error> {
+        let socket = open_uevent_socket()?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        std::thread::Builder::new()
+            .name("led-hotplug".to_owned())
+            .spawn(move || {
+                let mut buffer = vec![0; UEVENT_BUFFER_SIZE];
+                loop {
+                    // SAFETY: The buffer is valid for its whole length.
+                    let len = unsafe {
+                        libc::recv(
+                            socket.as_raw_fd(),
+                            buffer.as_mut_ptr().cast(),
+                            buffer.len(),
+                            0,
+                        )
+                    };
+                    if len < 0 {
+                        let err = io::Error::last_os_error();
+                        if err.kind() == io::ErrorKind::Interrupted {
+                            continue;
+                        }
+                        tracing::error!("Stop listening for LED hotplug events: `{err}`");
+                        break;
+                    }
+
+                    if let Some(event) = parse_uevent(&buffer[..len as usize]) {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { receiver })
+    }
+
+    /// Wait for the next LED node to be added or removed.
+    /// Returns [`None`] if the socket can't be read anymore.
+    pub async fn next_event(&mut self) -> Option<HotplugEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Return an event that is already queued without waiting.
+    pub fn try_next_event(&mut self) -> Option<HotplugEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+fn open_uevent_socket() -> Result<OwnedFd, io::Error> {
+    // SAFETY: Plain system calls, the file descriptor is owned by the returned value.
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_KOBJECT_UEVENT,
+        );
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = OwnedFd::from_raw_fd(fd);
+
+        let mut addr: libc::sockaddr_nl = std::mem::zeroed();
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = KERNEL_UEVENT_GROUP;
+        let res = libc::bind(
+            fd,
+            (&addr as *const libc::sockaddr_nl).cast(),
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        );
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(socket)
+    }
+}
+
+/// Parse a kernel uevent message like
+/// `add@/devices/.../leds/name\0ACTION=add\0SUBSYSTEM=leds\0...`.
+/// Returns [`None`] for other subsystems and actions.
+fn parse_uevent(message: &[u8]) -> Option<HotplugEvent> {
+    let mut action = None;
+    let mut devpath = None;
+    let mut subsystem = None;
+
+    // The first field is the summary `action@devpath`.
+    for field in message.split(|byte| *byte == 0).skip(1) {
+        let field = std::str::from_utf8(field).ok()?;
+        if let Some((key, value)) = field.split_once('=') {
+            match key {
+                "ACTION" => action = Some(value),
+                "DEVPATH" => devpath = Some(value),
+                "SUBSYSTEM" => subsystem = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    if subsystem? != "leds" {
+        return None;
+    }
+    let name = devpath?.rsplit('/').next()?.to_owned();
+    match action? {
+        "add" => Some(HotplugEvent::Added(name)),
+        "remove" => Some(HotplugEvent::Removed(name)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_uevent, HotplugEvent};
+
+    #[test]
+    fn uevent_messages() {
+        let added = b"add@/devices/pci0000:00/usb1/1-2/0003:1234:5678.0001/leds/input5::kbd_backlight\0\
+            ACTION=add\0DEVPATH=/devices/pci0000:00/usb1/1-2/0003:1234:5678.0001/leds/input5::kbd_backlight\0\
+            SUBSYSTEM=leds\0SEQNUM=4242\0";
+        assert_eq!(
+            parse_uevent(added),
+            Some(HotplugEvent::Added("input5::kbd_backlight".to_owned()))
+        );
+
+        let removed = b"remove@/devices/platform/leds/rgb:kbd_backlight\0ACTION=remove\0\
+            DEVPATH=/devices/platform/leds/rgb:kbd_backlight\0SUBSYSTEM=leds\0";
+        assert_eq!(
+            parse_uevent(removed),
+            Some(HotplugEvent::Removed("rgb:kbd_backlight".to_owned()))
+        );
+
+        let other = b"add@/devices/virtual/input/input5\0ACTION=add\0\
+            DEVPATH=/devices/virtual/input/input5\0SUBSYSTEM=input\0";
+        assert_eq!(parse_uevent(other), None);
+    }
+}
@@ -1,5 +1,9 @@
+use tailor_api::LedChannel;
+
+mod brightness_watcher;
 mod collection;
 mod controller;
+mod hotplug;
 
 /// A collection of controllers for LED devices.
 /// Stores a [`Vec`] of [`Controller`] and initializes by
@@ -16,6 +20,51 @@ pub struct Controller {
     pub device_name: String,
     pub function: String,
     max_brightness: u32,
+    /// Brightness in percent that is applied on top of the color.
+    brightness: u8,
+    /// Optional gamma correction for RGB devices.
+    gamma: Option<f32>,
+    /// The color channels of multicolor devices in the order of the `multi_index` file.
+    channels: Vec<LedChannel>,
+    /// The individually controllable zones of the device,
+    /// for example of keyboards with multiple backlight zones.
+    /// Most devices only have a single zone.
+    zones: Vec<Zone>,
+    brightness_watcher: Option<BrightnessWatcher>,
+}
+
+/// The sysfs files of a single LED node.
+#[derive(Debug)]
+struct Zone {
+    /// The name of the LED node in sysfs.
+    /// Empty if the controller wasn't created by a [`Collection`].
+    name: String,
     brightness_file: tokio_uring::fs::File,
     intensities_file: Option<tokio_uring::fs::File>,
 }
+
+/// A type that watches the `brightness_hw_changed` file
+/// of an LED device to detect brightness changes made by
+/// the firmware, for example through brightness hotkeys.
+#[derive(Debug)]
+pub struct BrightnessWatcher {
+    max_brightness: u32,
+    file: tokio_uring::fs::File,
+    last_value: Option<u32>,
+}
+
+/// Reported by the [`HotplugMonitor`] when an LED node
+/// is added or removed, for example by a USB keyboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotplugEvent {
+    /// The name of the new LED node.
+    Added(String),
+    /// The name of the removed LED node.
+    Removed(String),
+}
+
+/// A type that listens to the kernel uevents of the LED class.
+#[derive(Debug)]
+pub struct HotplugMonitor {
+    receiver: tokio::sync::mpsc::UnboundedReceiver<HotplugEvent>,
+}